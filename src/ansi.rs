@@ -0,0 +1,417 @@
+use std::ops::BitOr;
+use unicode_width::UnicodeWidthChar;
+
+/// A hyperlink over part of a line of output, taken from an OSC 8 escape sequence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hyperlink {
+    /// Byte range of the linked text in the line.
+    pub start: usize,
+    pub end: usize,
+    pub url: String,
+}
+
+/// Removes the OSC 8 hyperlink sequences from a line of output, returning the
+/// visible text and the links found in it.
+///
+/// A link is opened by `ESC ] 8 ; params ; URI ST` and closed by the same
+/// sequence with an empty URI, where `ST` is either `ESC \` or `BEL`. A link
+/// left open runs to the end of the line. Unterminated sequences are kept as-is.
+pub fn extract_hyperlinks(raw: &str) -> (String, Vec<Hyperlink>) {
+    const OPEN: &str = "\x1b]8;";
+    if !raw.contains(OPEN) {
+        return (raw.to_string(), Vec::new());
+    }
+    let mut text = String::with_capacity(raw.len());
+    let mut links = Vec::new();
+    let mut open: Option<(usize, String)> = None;
+    let mut rest = raw;
+    while let Some(pos) = rest.find(OPEN) {
+        text.push_str(&rest[..pos]);
+        let body = &rest[pos + OPEN.len()..];
+        let terminator = body
+            .find(['\x07', '\x1b'])
+            .filter(|&i| body[i..].starts_with('\x07') || body[i..].starts_with("\x1b\\"));
+        let Some(end) = terminator else {
+            rest = &rest[pos..];
+            break;
+        };
+        let url = body[..end].split_once(';').map_or("", |(_, url)| url);
+        if let Some((start, url)) = open.take() {
+            links.push(Hyperlink {
+                start,
+                end: text.len(),
+                url,
+            });
+        }
+        if !url.is_empty() {
+            open = Some((text.len(), url.to_string()));
+        }
+        let skip = if body[end..].starts_with('\x07') {
+            1
+        } else {
+            2
+        };
+        rest = &body[end + skip..];
+    }
+    text.push_str(rest);
+    if let Some((start, url)) = open {
+        links.push(Hyperlink {
+            start,
+            end: text.len(),
+            url,
+        });
+    }
+    links.retain(|link| link.end > link.start);
+    (text, links)
+}
+
+/// A color of an SGR sequence: an index of the 256-color palette, the first 16
+/// being the standard colors, or a 24-bit value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+/// A set of the text attributes of SGR sequences, combined with `|`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Modifier(u8);
+
+impl Modifier {
+    pub const BOLD: Modifier = Modifier(1);
+    pub const DIM: Modifier = Modifier(1 << 1);
+    pub const ITALIC: Modifier = Modifier(1 << 2);
+    pub const UNDERLINED: Modifier = Modifier(1 << 3);
+    pub const SLOW_BLINK: Modifier = Modifier(1 << 4);
+    pub const REVERSED: Modifier = Modifier(1 << 5);
+    pub const CROSSED_OUT: Modifier = Modifier(1 << 6);
+
+    pub fn contains(self, other: Modifier) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    fn without(self, other: Modifier) -> Modifier {
+        Modifier(self.0 & !other.0)
+    }
+}
+
+impl BitOr for Modifier {
+    type Output = Modifier;
+
+    fn bitor(self, other: Modifier) -> Modifier {
+        Modifier(self.0 | other.0)
+    }
+}
+
+/// Colors and attributes set by SGR sequences. Attributes can also be turned
+/// off, which matters when the style is laid over another one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub add_modifier: Modifier,
+    pub sub_modifier: Modifier,
+}
+
+impl Style {
+    pub fn fg(self, color: Color) -> Style {
+        Style {
+            fg: Some(color),
+            ..self
+        }
+    }
+
+    pub fn bg(self, color: Color) -> Style {
+        Style {
+            bg: Some(color),
+            ..self
+        }
+    }
+
+    pub fn add_modifier(self, modifier: Modifier) -> Style {
+        Style {
+            add_modifier: self.add_modifier | modifier,
+            sub_modifier: self.sub_modifier.without(modifier),
+            ..self
+        }
+    }
+
+    pub fn remove_modifier(self, modifier: Modifier) -> Style {
+        Style {
+            add_modifier: self.add_modifier.without(modifier),
+            sub_modifier: self.sub_modifier | modifier,
+            ..self
+        }
+    }
+}
+
+#[cfg(feature = "tui")]
+impl From<Color> for ratatui::style::Color {
+    fn from(color: Color) -> Self {
+        match color {
+            Color::Indexed(i) => ratatui::style::Color::Indexed(i),
+            Color::Rgb(r, g, b) => ratatui::style::Color::Rgb(r, g, b),
+        }
+    }
+}
+
+#[cfg(feature = "tui")]
+impl From<Modifier> for ratatui::style::Modifier {
+    fn from(modifier: Modifier) -> Self {
+        use ratatui::style::Modifier as M;
+        [
+            (Modifier::BOLD, M::BOLD),
+            (Modifier::DIM, M::DIM),
+            (Modifier::ITALIC, M::ITALIC),
+            (Modifier::UNDERLINED, M::UNDERLINED),
+            (Modifier::SLOW_BLINK, M::SLOW_BLINK),
+            (Modifier::REVERSED, M::REVERSED),
+            (Modifier::CROSSED_OUT, M::CROSSED_OUT),
+        ]
+        .into_iter()
+        .filter(|(ours, _)| modifier.contains(*ours))
+        .fold(M::empty(), |all, (_, theirs)| all | theirs)
+    }
+}
+
+#[cfg(feature = "tui")]
+impl From<Style> for ratatui::style::Style {
+    fn from(style: Style) -> Self {
+        ratatui::style::Style {
+            fg: style.fg.map(Into::into),
+            bg: style.bg.map(Into::into),
+            add_modifier: style.add_modifier.into(),
+            sub_modifier: style.sub_modifier.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// A styled part of a line of output, taken from ANSI SGR escape sequences.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StyledRange {
+    /// Byte range of the styled text in the line.
+    pub start: usize,
+    pub end: usize,
+    pub style: Style,
+}
+
+/// Removes the ANSI CSI escape sequences from a line of output, returning the
+/// visible text and the parts styled by the SGR ones (`ESC [ ... m`). Other CSI
+/// sequences, such as cursor moves, are dropped, and the byte ranges of `links`
+/// are moved to match the text left. Unterminated sequences are kept as-is.
+///
+/// Colors are the 16 standard ones, the 256 indexed ones and 24-bit ones, along
+/// with bold, dim, italic, underline, blink, reverse and crossed-out text.
+pub fn extract_styles(raw: &str, links: &mut [Hyperlink]) -> (String, Vec<StyledRange>) {
+    const CSI: &str = "\x1b[";
+    if !raw.contains(CSI) {
+        return (raw.to_string(), Vec::new());
+    }
+    let mut text = String::with_capacity(raw.len());
+    let mut styles = Vec::new();
+    let mut style = Style::default();
+    let mut run_start = 0;
+    // End in `raw` of each removed sequence, with the bytes removed up to there.
+    let mut removed: Vec<(usize, usize)> = Vec::new();
+    let mut rest = raw;
+    while let Some(pos) = rest.find(CSI) {
+        text.push_str(&rest[..pos]);
+        let body = &rest[pos + CSI.len()..];
+        let Some(end) = body.find(|c: char| ('\x40'..='\x7e').contains(&c)) else {
+            rest = &rest[pos..];
+            break;
+        };
+        if body[..end].chars().any(|c| !('\x20'..='\x3f').contains(&c)) {
+            // Not a well-formed sequence: keep the escape and go on after it.
+            text.push_str(CSI);
+            rest = body;
+            continue;
+        }
+        if body[end..].starts_with('m') {
+            let next = apply_sgr(style, &body[..end]);
+            if next != style {
+                if style != Style::default() && text.len() > run_start {
+                    styles.push(StyledRange {
+                        start: run_start,
+                        end: text.len(),
+                        style,
+                    });
+                }
+                style = next;
+                run_start = text.len();
+            }
+        }
+        let consumed = raw.len() - body.len() + end + 1;
+        removed.push((consumed, consumed - text.len()));
+        rest = &body[end + 1..];
+    }
+    text.push_str(rest);
+    if style != Style::default() && text.len() > run_start {
+        styles.push(StyledRange {
+            start: run_start,
+            end: text.len(),
+            style,
+        });
+    }
+    let moved = |offset: usize| {
+        let before = removed.partition_point(|&(end, _)| end <= offset);
+        offset - before.checked_sub(1).map_or(0, |i| removed[i].1)
+    };
+    for link in links.iter_mut() {
+        link.start = moved(link.start);
+        link.end = moved(link.end);
+    }
+    (text, styles)
+}
+
+/// Columns between tab stops, for `show_controls`.
+const TAB_WIDTH: usize = 8;
+
+/// Makes the control characters left in a line of output harmless to the
+/// terminal: tabs are expanded with spaces to the next tab stop, the other C0
+/// controls and DEL are shown as their Unicode control pictures, such as `␀`
+/// or `␍`, and C1 controls as `�`. The byte ranges of `links` and `styles` are
+/// moved to match the text.
+pub fn show_controls(raw: &str, links: &mut [Hyperlink], styles: &mut [StyledRange]) -> String {
+    if !raw.chars().any(char::is_control) {
+        return raw.to_string();
+    }
+    let mut text = String::with_capacity(raw.len());
+    let mut column = 0;
+    // End in `raw` of each replaced character, with the bytes added up to there.
+    let mut added: Vec<(usize, usize)> = Vec::new();
+    for (i, c) in raw.char_indices() {
+        match c {
+            '\t' => {
+                let spaces = TAB_WIDTH - column % TAB_WIDTH;
+                text.extend(std::iter::repeat_n(' ', spaces));
+                column += spaces;
+            }
+            '\x00'..='\x1f' => {
+                text.push(char::from_u32(0x2400 + c as u32).unwrap_or('\u{fffd}'));
+                column += 1;
+            }
+            '\x7f' => {
+                text.push('\u{2421}');
+                column += 1;
+            }
+            c if c.is_control() => {
+                text.push('\u{fffd}');
+                column += 1;
+            }
+            c => {
+                text.push(c);
+                column += c.width().unwrap_or(0);
+                continue;
+            }
+        }
+        let end = i + c.len_utf8();
+        added.push((end, text.len() - end));
+    }
+    let moved = |offset: usize| {
+        let before = added.partition_point(|&(end, _)| end <= offset);
+        offset + before.checked_sub(1).map_or(0, |i| added[i].1)
+    };
+    for link in links.iter_mut() {
+        link.start = moved(link.start);
+        link.end = moved(link.end);
+    }
+    for styled in styles.iter_mut() {
+        styled.start = moved(styled.start);
+        styled.end = moved(styled.end);
+    }
+    text
+}
+
+/// Applies the parameters of an SGR sequence to a style. Unknown ones are ignored.
+fn apply_sgr(mut style: Style, params: &str) -> Style {
+    let codes: Vec<u16> = params
+        .split([';', ':'])
+        .map(|code| code.parse().unwrap_or(0))
+        .collect();
+    let mut codes = codes.iter().copied();
+    while let Some(code) = codes.next() {
+        style = match code {
+            0 => Style::default(),
+            1 => style.add_modifier(Modifier::BOLD),
+            2 => style.add_modifier(Modifier::DIM),
+            3 => style.add_modifier(Modifier::ITALIC),
+            4 => style.add_modifier(Modifier::UNDERLINED),
+            5 => style.add_modifier(Modifier::SLOW_BLINK),
+            7 => style.add_modifier(Modifier::REVERSED),
+            9 => style.add_modifier(Modifier::CROSSED_OUT),
+            22 => style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style.remove_modifier(Modifier::ITALIC),
+            24 => style.remove_modifier(Modifier::UNDERLINED),
+            25 => style.remove_modifier(Modifier::SLOW_BLINK),
+            27 => style.remove_modifier(Modifier::REVERSED),
+            29 => style.remove_modifier(Modifier::CROSSED_OUT),
+            30..=37 => style.fg(Color::Indexed(code as u8 - 30)),
+            90..=97 => style.fg(Color::Indexed(code as u8 - 90 + 8)),
+            40..=47 => style.bg(Color::Indexed(code as u8 - 40)),
+            100..=107 => style.bg(Color::Indexed(code as u8 - 100 + 8)),
+            38 => match extended_color(&mut codes) {
+                Some(color) => style.fg(color),
+                None => style,
+            },
+            48 => match extended_color(&mut codes) {
+                Some(color) => style.bg(color),
+                None => style,
+            },
+            39 => Style { fg: None, ..style },
+            49 => Style { bg: None, ..style },
+            _ => style,
+        };
+    }
+    style
+}
+
+/// Reads the color of a `38` or `48` parameter: `5;N` for an indexed color or
+/// `2;R;G;B` for a 24-bit one.
+fn extended_color(codes: &mut impl Iterator<Item = u16>) -> Option<Color> {
+    let mut component = || codes.next().map(|c| c.min(255) as u8);
+    match component()? {
+        5 => Some(Color::Indexed(component()?)),
+        2 => Some(Color::Rgb(component()?, component()?, component()?)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(start: usize, end: usize, url: &str) -> Hyperlink {
+        Hyperlink {
+            start,
+            end,
+            url: url.to_string(),
+        }
+    }
+
+    #[test]
+    fn hyperlinks_closed_with_st_or_bel_are_extracted() {
+        let st = "see \x1b]8;;https://x\x1b\\text\x1b]8;;\x1b\\ now";
+        assert_eq!(
+            extract_hyperlinks(st),
+            ("see text now".to_string(), vec![link(4, 8, "https://x")])
+        );
+        let bel = "see \x1b]8;id=1;https://x\x07text\x1b]8;;\x07 now";
+        assert_eq!(extract_hyperlinks(bel), extract_hyperlinks(st));
+    }
+
+    #[test]
+    fn open_and_unterminated_hyperlinks() {
+        assert_eq!(
+            extract_hyperlinks("\x1b]8;;https://x\x1b\\to the end"),
+            ("to the end".to_string(), vec![link(0, 10, "https://x")])
+        );
+        let unterminated = "cut \x1b]8;;https://x";
+        assert_eq!(
+            extract_hyperlinks(unterminated),
+            (unterminated.to_string(), vec![])
+        );
+        assert_eq!(extract_hyperlinks("plain"), ("plain".to_string(), vec![]));
+    }
+}
@@ -0,0 +1,78 @@
+use crate::config::CgroupConfig;
+use std::{fs, io, path::PathBuf};
+
+/// Period of the CPU bandwidth limit, in microseconds.
+const CPU_PERIOD: u64 = 100_000;
+
+/// A cgroup v2 prepared for the children, with its limits applied.
+pub struct Cgroup {
+    pub path: PathBuf,
+    /// Whether the runner created the cgroup, and so removes it on exit.
+    pub created: bool,
+}
+
+impl Cgroup {
+    /// Creates the configured cgroup if it doesn't exist and writes its limits.
+    ///
+    /// Fails when cgroup v2 isn't mounted, the parent cgroup can't be written,
+    /// or a limit is for a controller not enabled in the parent.
+    #[cfg(target_os = "linux")]
+    pub fn prepare(config: &CgroupConfig) -> io::Result<Self> {
+        let path = PathBuf::from(&config.path);
+        let parent = path.parent().unwrap_or(&path);
+        if !parent.join("cgroup.controllers").is_file() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is not in a cgroup v2 hierarchy", parent.display()),
+            ));
+        }
+        let created = !path.exists();
+        if created {
+            fs::create_dir(&path)?;
+        }
+        let cgroup = Cgroup { path, created };
+        let limits = [
+            (
+                "cpu.max",
+                config
+                    .cpus
+                    .map(|cpus| format!("{} {}", (cpus * CPU_PERIOD as f64) as u64, CPU_PERIOD)),
+            ),
+            ("memory.max", config.memory_max.map(|m| m.0.to_string())),
+            ("pids.max", config.pids_max.map(|n| n.to_string())),
+        ];
+        for (file, value) in limits {
+            if let Some(value) = value
+                && let Err(e) = fs::write(cgroup.path.join(file), value)
+            {
+                cgroup.remove();
+                return Err(io::Error::new(
+                    e.kind(),
+                    format!("cannot set {}: {}", file, e),
+                ));
+            }
+        }
+        Ok(cgroup)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn prepare(_config: &CgroupConfig) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "cgroup is only supported on Linux",
+        ))
+    }
+
+    /// Path of the file a process writes `0` to in order to join the cgroup.
+    pub fn procs_file(&self) -> PathBuf {
+        self.path.join("cgroup.procs")
+    }
+
+    /// Removes the cgroup if the runner created it. This only succeeds once
+    /// every process in it has exited.
+    pub fn remove(&self) {
+        if self.created {
+            let _ = fs::remove_dir(&self.path);
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::{
+    io::{self, Write},
+    process::{Command, Stdio},
+};
+
+/// Programs that put their input on the system clipboard, with the arguments
+/// they need and the environment variable that tells whether they can work.
+const PROGRAMS: [(&str, &[&str], Option<&str>); 4] = [
+    ("pbcopy", &[], None),
+    ("wl-copy", &[], Some("WAYLAND_DISPLAY")),
+    ("xclip", &["-selection", "clipboard"], Some("DISPLAY")),
+    ("xsel", &["--clipboard", "--input"], Some("DISPLAY")),
+];
+
+/// Copies text to the system clipboard with the first of `pbcopy`, `wl-copy`,
+/// `xclip` or `xsel` that is installed and usable.
+pub fn copy(text: &str) -> io::Result<()> {
+    for (program, args, display) in PROGRAMS {
+        if display.is_some_and(|var| std::env::var_os(var).is_none()) {
+            continue;
+        }
+        // The X11 tools keep running to serve the selection, so their output
+        // is not read.
+        let mut child = match Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "{} exited with {}",
+                program, status
+            )));
+        }
+        return Ok(());
+    }
+    Err(io::Error::other(
+        "no clipboard available (needs pbcopy, wl-copy, xclip or xsel and a display)",
+    ))
+}
@@ -1,25 +1,1184 @@
-use serde::Deserialize;
-use std::{fs::File, io::BufReader};
+use crate::error::RunnerError;
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufReader, BufWriter},
+    ops::Deref,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+#[cfg(feature = "tui")]
+mod view;
+#[cfg(feature = "tui")]
+pub use view::{ColorName, Highlight, Key, Keybindings, Keyword};
+
+/// Format of the output timestamps when `timestamp_format` is not set.
+pub const DEFAULT_TIMESTAMP_FORMAT: &str = "%H:%M:%S%.3f";
+
+/// Number of lines kept per pane when `max_lines` is not set.
+pub const DEFAULT_MAX_LINES: usize = 5000;
+
+/// Milliseconds between samples of resource usage when `usage_interval_ms` is not set.
+pub const DEFAULT_USAGE_INTERVAL_MS: u64 = 2000;
+
+/// Output lines on their way to the TUI per process when `output_capacity` is not set.
+pub const DEFAULT_OUTPUT_CAPACITY: usize = 100;
+
+/// Commands waiting per process when `command_capacity` is not set.
+pub const DEFAULT_COMMAND_CAPACITY: usize = 10;
+
+/// Number of processes accepted when `max_processes` is not set.
+pub const DEFAULT_MAX_PROCESSES: usize = 20;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
     pub processes: Vec<ProcessConfig>,
+    /// Default wrap setting for panes that don't override it.
+    #[serde(default)]
+    pub wrap: bool,
+    /// Default line-number gutter setting for panes that don't override it.
+    #[serde(default)]
+    pub line_numbers: bool,
+    /// Print a per-process summary to stdout when the runner exits.
+    #[serde(default)]
+    pub summary: bool,
+    /// Default for forcing line-buffered output through `stdbuf` (Linux only).
+    #[serde(default)]
+    pub line_buffered: bool,
+    /// Add a read-only pane collecting error-level lines from every process.
+    #[serde(default)]
+    pub error_pane: bool,
+    /// Maximum number of processes accepted, `DEFAULT_MAX_PROCESSES` if not set.
+    #[serde(default)]
+    pub max_processes: Option<usize>,
+    /// Maximum width of the panes, in columns. Wider terminals center them.
+    #[serde(default)]
+    pub max_width: Option<u16>,
+    /// How the panes are arranged on screen.
+    #[serde(default)]
+    pub layout: PaneLayout,
+    /// Maximum number of processes starting at the same time; further starts wait.
+    #[serde(default)]
+    pub max_concurrent_starts: Option<usize>,
+    /// File recording the running process groups, to clean up after a crashed run (Unix only).
+    #[serde(default)]
+    pub state_file: Option<String>,
+    /// Default number of lines kept per pane, `DEFAULT_MAX_LINES` if not set; 0 keeps every line.
+    #[serde(default)]
+    pub max_lines: Option<usize>,
+    /// Upper bound on the memory used by all pane buffers together.
+    #[serde(default)]
+    pub buffer_budget: Option<ByteSize>,
+    /// Unix socket accepting control commands while the runner is up.
+    #[serde(default)]
+    pub control_socket: Option<String>,
+    /// Default template of the process pane titles.
+    #[serde(default)]
+    pub title_format: Option<TitleFormat>,
+    /// Niceness the processes run with, from -20 to 19 (Unix only).
+    #[serde(default)]
+    pub nice: Option<i32>,
+    /// Control group v2 the processes are placed in, with resource limits (Linux only).
+    #[serde(default)]
+    pub cgroup: Option<CgroupConfig>,
+    /// File keeping the search history across runs.
+    #[serde(default)]
+    pub search_history: Option<String>,
+    /// Show the configuration file and when it was last modified in the top row.
+    #[serde(default)]
+    pub show_config: bool,
+    /// Show the time each output line was read at, before it.
+    #[serde(default)]
+    pub timestamps: bool,
+    /// `strftime` format of the timestamps, `DEFAULT_TIMESTAMP_FORMAT` if not set.
+    #[serde(default)]
+    pub timestamp_format: Option<String>,
+    /// Keys of the actions bound to other keys than the default ones.
+    #[cfg(feature = "tui")]
+    #[serde(default)]
+    pub keybindings: Keybindings,
+    /// Ask for confirmation before quitting with `q`; on unless set to `false`.
+    #[serde(default)]
+    pub confirm_quit: Option<bool>,
+    /// Number of last lines of the buffer copied with `Y`; all of them if unset.
+    #[serde(default)]
+    pub copy_lines: Option<usize>,
+    /// Quit once every process has ended, for a batch of one-shot tasks.
+    #[serde(default)]
+    pub run_to_completion: bool,
+    /// Apply the configuration file again when it changes on disk, as with `L`.
+    #[serde(default)]
+    pub watch_config: bool,
+    /// Show the CPU and memory used by each process in its pane title.
+    #[serde(default)]
+    pub show_usage: bool,
+    /// Refuse to load the configuration when the working directory or the
+    /// command of a process can't be found.
+    #[serde(default)]
+    pub check_commands: bool,
+    /// Show how long each process has been up and how many times it restarted
+    /// in its pane title.
+    #[serde(default)]
+    pub show_uptime: bool,
+    /// Default for counting the restarts shown in the titles from the last start
+    /// or restart asked for by the user only.
+    #[serde(default)]
+    pub reset_restarts: bool,
+    /// Milliseconds between samples of the usage, `DEFAULT_USAGE_INTERVAL_MS` if not set.
+    #[serde(default)]
+    pub usage_interval_ms: Option<u64>,
+    /// Show the latest line from any process in the top row of the screen.
+    #[serde(default)]
+    pub ticker: bool,
+    /// Number of recent output lines kept per process for the library API.
+    #[serde(default)]
+    pub recent_output: Option<usize>,
+    /// Output lines of a process on their way to the TUI at once,
+    /// `DEFAULT_OUTPUT_CAPACITY` if not set.
+    #[serde(default)]
+    pub output_capacity: Option<usize>,
+    /// Commands, such as starts and stops, waiting for a process at once,
+    /// `DEFAULT_COMMAND_CAPACITY` if not set.
+    #[serde(default)]
+    pub command_capacity: Option<usize>,
+    /// Lines of a stream of a process kept waiting when the TUI falls behind;
+    /// beyond it the oldest are dropped. Unlimited if not set.
+    #[serde(default)]
+    pub max_queued_lines: Option<usize>,
+    /// What to do once every process with `ready_when` is ready.
+    #[serde(default)]
+    pub on_ready: Option<OnReady>,
+    /// Colors given to the output lines by their contents.
+    #[cfg(feature = "tui")]
+    #[serde(default)]
+    pub highlight: Option<Highlight>,
+    /// Directory of the configuration file, that relative `cwd` paths are taken from.
+    #[serde(skip)]
+    pub dir: PathBuf,
+}
+
+/// Actions taken when the whole stack becomes ready.
+#[derive(Debug, Deserialize, Clone)]
+pub struct OnReady {
+    /// Shown in the status bar.
+    #[serde(default)]
+    pub message: Option<String>,
+    /// Run through the shell, with its output discarded.
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Ring the terminal bell.
+    #[serde(default)]
+    pub beep: bool,
 }
 
+/// A cgroup v2 for all the processes, created if missing, and its limits.
 #[derive(Debug, Deserialize, Clone)]
+pub struct CgroupConfig {
+    /// Directory of the cgroup, e.g. `/sys/fs/cgroup/starter`.
+    pub path: String,
+    /// CPU time available, in number of CPUs, e.g. `1.5`.
+    #[serde(default)]
+    pub cpus: Option<f64>,
+    #[serde(default)]
+    pub memory_max: Option<ByteSize>,
+    /// Maximum number of processes and threads.
+    #[serde(default)]
+    pub pids_max: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct ProcessConfig {
     pub name: String,
+    #[serde(default)]
     pub command: String,
+    #[serde(default)]
     pub args: Vec<String>,
-    pub cwd: String,
+    /// Command and arguments in one string, such as `cargo watch -x run`, split
+    /// into `command` and `args` when the configuration is loaded.
+    #[serde(default)]
+    pub cmd: Option<String>,
+    /// Working directory; the directory of the configuration file when left out.
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Keep the standard input of the process open for the lines typed in input
+    /// mode, instead of giving it none.
+    #[serde(default)]
+    pub stdin: bool,
+    /// Run `command` as a shell command line, with `sh -c` (`cmd /C` on Windows),
+    /// so it can use pipes, redirections and globs; `args` are passed to it.
+    #[serde(default)]
+    pub shell: bool,
+    /// Environment variables set for the process, on top of the inherited ones.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// File of `KEY=VALUE` lines, relative to `cwd`, read before `env` at each start.
+    #[serde(default)]
+    pub env_file: Option<String>,
+    /// Start from an empty environment instead of inheriting the runner's.
+    #[serde(default, alias = "clear_env")]
+    pub env_clear: bool,
+    /// Variables of the runner's environment kept when `env_clear` is set.
+    #[serde(default)]
+    pub env_passthrough: Vec<String>,
+    /// Fail to start when `command`, `args` or `cwd` refer to an unset variable,
+    /// instead of expanding it to nothing.
+    #[serde(default)]
+    pub strict_env: bool,
+    /// Overrides the global `title_format` for this pane.
+    #[serde(default)]
+    pub title_format: Option<TitleFormat>,
+    /// Color of the pane border, instead of one picked from a palette by position.
+    #[cfg(feature = "tui")]
+    #[serde(default)]
+    pub color: Option<ColorName>,
+    /// Overrides the global `max_lines` default for this pane.
+    #[serde(default)]
+    pub max_lines: Option<usize>,
+    /// Overrides the global `wrap` default for this pane.
+    #[serde(default)]
+    pub wrap: Option<bool>,
+    /// Overrides the global `line_numbers` default for this pane.
+    #[serde(default)]
+    pub line_numbers: Option<bool>,
+    /// Overrides the global `line_buffered` default for this process.
+    #[serde(default)]
+    pub line_buffered: Option<bool>,
+    /// Overrides the global `reset_restarts` default for this process.
+    #[serde(default)]
+    pub reset_restarts: Option<bool>,
+    /// Render the ANSI colors of the output; on unless set to `false`, which
+    /// shows the escape sequences literally.
+    #[serde(default)]
+    pub ansi: Option<bool>,
+    /// Names of processes whose restart also restarts this one.
+    #[serde(default)]
+    pub restart_with: Vec<String>,
+    /// Milliseconds to wait before each start, once the `depends_on` processes are ready.
+    #[serde(default)]
+    pub start_delay_ms: Option<u64>,
+    /// Names of processes that must be ready before this one starts.
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Extracts a progress percentage from output lines, shown as a gauge in the pane.
+    #[serde(default)]
+    pub progress_pattern: Option<Pattern>,
+    /// Output lines matching any of these patterns are dropped.
+    #[serde(default)]
+    pub ignore: Vec<Pattern>,
+    /// Output line announcing that the process is ready, used to measure its startup.
+    #[serde(default)]
+    pub ready_when: Option<Pattern>,
+    /// TCP port on 127.0.0.1 that accepts connections once the process is ready,
+    /// checked instead of its output lines.
+    #[serde(default)]
+    pub ready_port: Option<u16>,
+    /// Milliseconds a start has to become ready before its readiness check fails.
+    #[serde(default)]
+    pub ready_timeout_ms: Option<u64>,
+    /// Milliseconds without any output, from the start or the last line, after
+    /// which the process is reported as silent.
+    #[serde(default)]
+    pub idle_timeout_ms: Option<u64>,
+    /// Ask for confirmation before stopping this process from the TUI.
+    #[serde(default)]
+    pub confirm_stop: bool,
+    /// Show JSON output lines as the values of these fields instead of the raw object.
+    #[serde(default)]
+    pub json_fields: Vec<String>,
+    /// Number of attempts at running the process, counting failed spawns and
+    /// exits with a non-zero code, before it is marked as failed.
+    #[serde(default)]
+    pub max_attempts: Option<u32>,
+    /// File the output is appended to, where `{name}` stands for the process name.
+    #[serde(default)]
+    pub log_file: Option<String>,
+    /// Whether the process is started again when it ends on its own.
+    #[serde(default)]
+    pub restart: RestartPolicy,
+    /// Highlight what changed in lines matching this pattern since the previous one.
+    #[serde(default)]
+    pub diff_pattern: Option<Pattern>,
+    /// Scroll the pane to the first error of a new failure and stop following the output.
+    #[serde(default)]
+    pub jump_to_error: bool,
+    /// File, relative to `cwd`, where a command that daemonizes itself writes
+    /// the PID of the process it leaves running (Unix only).
+    #[serde(default)]
+    pub pidfile: Option<String>,
+    /// Whether the process is started with the others; when false it is left
+    /// out of starting and restarting all (`A`, `R`) too, and only its own pane
+    /// starts it.
+    #[serde(default)]
+    pub enabled: Option<bool>,
+    /// Whether the process is started at launch; when false it waits for the
+    /// user, but unlike a disabled process, `A` starts it with the others.
+    #[serde(default)]
+    pub auto_start: Option<bool>,
+}
+
+impl ProcessConfig {
+    /// Whether the process is started automatically: `enabled` unless set to false.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+
+    /// Whether the process is started along with the others: it is enabled and
+    /// `auto_start` isn't set to false.
+    pub fn starts_automatically(&self) -> bool {
+        self.is_enabled() && self.auto_start.unwrap_or(true)
+    }
+
+    /// Checks that the working directory exists and the command can be found,
+    /// as a path from it or in `PATH`. What holds variables is only known at
+    /// start, and the command line of a `shell` process is left to the shell.
+    pub fn check_launch(&self) -> Result<(), String> {
+        let cwd = self.dir();
+        if cwd.to_string_lossy().contains('$') {
+            return Ok(());
+        }
+        if !cwd.is_dir() {
+            return Err(format!(
+                "working directory '{}' does not exist",
+                cwd.display()
+            ));
+        }
+        let found = if self.shell || self.command.contains('$') {
+            true
+        } else if self.command.contains(std::path::MAIN_SEPARATOR) {
+            cwd.join(&self.command).is_file()
+        } else {
+            find_in_path(&self.command).is_some()
+        };
+        if !found {
+            return Err(format!("command '{}' not found", self.command));
+        }
+        Ok(())
+    }
+
+    /// Directory the process runs in: its `cwd`, or the one of the runner.
+    pub fn dir(&self) -> &Path {
+        Path::new(self.cwd.as_deref().unwrap_or("."))
+    }
+
+    /// Whether the process says when it is ready, by `ready_when` or `ready_port`.
+    pub fn checks_ready(&self) -> bool {
+        self.ready_when.is_some() || self.ready_port.is_some()
+    }
+
+    /// Whether `other` runs the same command line in the same environment:
+    /// `command`, `args`, `cwd`, `shell` and the `env` settings are equal.
+    pub fn same_launch(&self, other: &ProcessConfig) -> bool {
+        self.command == other.command
+            && self.args == other.args
+            && self.cwd == other.cwd
+            && self.shell == other.shell
+            && self.env == other.env
+            && self.env_file == other.env_file
+            && self.env_clear == other.env_clear
+            && self.env_passthrough == other.env_passthrough
+            && self.strict_env == other.strict_env
+    }
+
+    /// The command followed by its arguments, separated by spaces.
+    pub fn command_line(&self) -> String {
+        std::iter::once(&self.command)
+            .chain(&self.args)
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Takes the settings compared by `same_launch` from `other`.
+    pub fn take_launch(&mut self, other: &ProcessConfig) {
+        self.command = other.command.clone();
+        self.args = other.args.clone();
+        self.cwd = other.cwd.clone();
+        self.shell = other.shell;
+        self.env = other.env.clone();
+        self.env_file = other.env_file.clone();
+        self.env_clear = other.env_clear;
+        self.env_passthrough = other.env_passthrough.clone();
+        self.strict_env = other.strict_env;
+    }
 }
 
-pub fn load_config(file_path: &str) -> Result<Config, Box<dyn std::error::Error>> {
-    let file = File::open(file_path).expect("Failed to open config file");
-    let reader = BufReader::new(file);
-    let config: Config = serde_yaml::from_reader(reader)?;
-    if config.processes.len() < 1 || config.processes.len() > 6 {
-        return Err("Number of processes must be between 1 and 6".into());
+/// Arrangement of the panes on screen.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PaneLayout {
+    /// Stacked from top to bottom.
+    #[default]
+    Vertical,
+    /// Side by side from left to right.
+    Horizontal,
+    /// In rows of about the square root of their number.
+    Grid,
+}
+
+impl PaneLayout {
+    /// The layout after this one, cycling through all of them.
+    pub fn next(self) -> Self {
+        match self {
+            PaneLayout::Vertical => PaneLayout::Horizontal,
+            PaneLayout::Horizontal => PaneLayout::Grid,
+            PaneLayout::Grid => PaneLayout::Vertical,
+        }
+    }
+}
+
+impl std::fmt::Display for PaneLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            PaneLayout::Vertical => "vertical",
+            PaneLayout::Horizontal => "horizontal",
+            PaneLayout::Grid => "grid",
+        })
+    }
+}
+
+/// When a process that ended on its own is started again, after a delay that
+/// grows with each restart in a row.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RestartPolicy {
+    #[default]
+    Never,
+    /// After it could not be spawned, or exited with a code other than 0 or by a signal.
+    OnFailure,
+    /// Whenever it ends, even with code 0.
+    Always,
+}
+
+/// A regular expression from the configuration, compiled when it is loaded so
+/// that invalid patterns are reported up front.
+#[derive(Debug, Clone)]
+pub struct Pattern(Regex);
+
+impl Deref for Pattern {
+    type Target = Regex;
+
+    fn deref(&self) -> &Regex {
+        &self.0
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        Regex::new(&source)
+            .map(Pattern)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// A size in bytes: a plain number, or one with a `K`, `M` or `G` suffix
+/// (powers of 1024), optionally followed by `B`, e.g. `64M` or `512KB`.
+#[derive(Debug, Clone, Copy)]
+pub struct ByteSize(pub usize);
+
+impl<'de> Deserialize<'de> for ByteSize {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Number(usize),
+            Text(String),
+        }
+        let text = match Raw::deserialize(deserializer)? {
+            Raw::Number(n) => return Ok(ByteSize(n)),
+            Raw::Text(text) => text,
+        };
+        let invalid = || serde::de::Error::custom(format!("invalid size '{}'", text));
+        let trimmed = text.trim();
+        let trimmed = trimmed.strip_suffix(['B', 'b']).unwrap_or(trimmed);
+        let (digits, unit) = match trimmed.char_indices().last() {
+            Some((i, c)) if c.is_ascii_alphabetic() => (&trimmed[..i], Some(c)),
+            _ => (trimmed, None),
+        };
+        let multiplier: usize = match unit.map(|c| c.to_ascii_uppercase()) {
+            None => 1,
+            Some('K') => 1 << 10,
+            Some('M') => 1 << 20,
+            Some('G') => 1 << 30,
+            Some(_) => return Err(invalid()),
+        };
+        let value: usize = digits.trim().parse().map_err(|_| invalid())?;
+        value
+            .checked_mul(multiplier)
+            .map(ByteSize)
+            .ok_or_else(invalid)
+    }
+}
+
+/// A value that can be shown in a pane title through `title_format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TitleField {
+    Name,
+    State,
+    Uptime,
+    Exit,
+    Health,
+    Restarts,
+}
+
+#[derive(Debug, Clone)]
+enum TitlePart {
+    Text(String),
+    Field(TitleField),
+}
+
+/// A pane title template, such as `{name} up {uptime}`, checked when the
+/// configuration is loaded. Placeholders name a `TitleField` in lower case;
+/// `{{` and `}}` stand for literal braces.
+#[derive(Debug, Clone)]
+pub struct TitleFormat(Vec<TitlePart>);
+
+impl TitleFormat {
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next().ok_or("unterminated placeholder")? {
+                            '}' => break,
+                            c => name.push(c),
+                        }
+                    }
+                    let field = match name.as_str() {
+                        "name" => TitleField::Name,
+                        "state" => TitleField::State,
+                        "uptime" => TitleField::Uptime,
+                        "exit" => TitleField::Exit,
+                        "health" => TitleField::Health,
+                        "restarts" => TitleField::Restarts,
+                        _ => return Err(format!("unknown placeholder '{{{}}}'", name)),
+                    };
+                    if !text.is_empty() {
+                        parts.push(TitlePart::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(TitlePart::Field(field));
+                }
+                '}' => return Err("unmatched '}'".to_string()),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(TitlePart::Text(text));
+        }
+        Ok(TitleFormat(parts))
+    }
+
+    /// Fills in the template, taking the value of each placeholder from `value`.
+    pub fn render(&self, value: impl Fn(TitleField) -> String) -> String {
+        self.0
+            .iter()
+            .map(|part| match part {
+                TitlePart::Text(text) => text.clone(),
+                TitlePart::Field(field) => value(*field),
+            })
+            .collect()
+    }
+}
+
+impl<'de> Deserialize<'de> for TitleFormat {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let template = String::deserialize(deserializer)?;
+        TitleFormat::parse(&template).map_err(|e| {
+            serde::de::Error::custom(format!("invalid title_format '{}': {}", template, e))
+        })
+    }
+}
+
+/// Syntax of a configuration file, chosen from its extension.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Yaml,
+    Toml,
+    /// Unknown extension, read as YAML.
+    Other,
+}
+
+impl Format {
+    fn of(file_path: &str) -> Self {
+        match Path::new(file_path).extension().and_then(|e| e.to_str()) {
+            Some("yaml" | "yml") => Format::Yaml,
+            Some("toml") => Format::Toml,
+            _ => Format::Other,
+        }
+    }
+}
+
+/// Loads and checks the configuration, as YAML or, for `.toml` files, TOML.
+pub fn load_config(file_path: &str) -> Result<Config, RunnerError> {
+    let contents = std::fs::read_to_string(file_path).map_err(RunnerError::ConfigNotFound)?;
+    let mut config: Config = match Format::of(file_path) {
+        Format::Yaml => serde_yaml::from_str(&contents)?,
+        Format::Toml => toml::from_str(&contents)?,
+        Format::Other => serde_yaml::from_str(&contents).map_err(|e| {
+            RunnerError::ConfigParse(format!(
+                "could not read the file as YAML ({}); use a .yaml, .yml or .toml extension",
+                e
+            ))
+        })?,
+    };
+    let base = Path::new(file_path).parent().unwrap_or(Path::new(""));
+    config.dir = base.to_path_buf();
+    for proc in &mut config.processes {
+        proc.cwd = match &proc.cwd {
+            Some(cwd) => Some(resolve_cwd(cwd, base)),
+            None => (!base.as_os_str().is_empty()).then(|| base.to_string_lossy().into_owned()),
+        };
+        if let Some(line) = proc.cmd.take() {
+            if !proc.command.is_empty() || !proc.args.is_empty() {
+                return Err(
+                    format!("'{}' can't have both cmd and command or args", proc.name).into(),
+                );
+            }
+            if proc.shell {
+                // The shell splits the command line itself.
+                proc.command = line;
+            } else {
+                let mut words = split_words(&line)
+                    .map_err(|e| format!("cmd of '{}': {}", proc.name, e))?
+                    .into_iter();
+                proc.command = words.next().unwrap_or_default();
+                proc.args = words.collect();
+            }
+        }
+        if proc.command.is_empty() {
+            return Err(format!("'{}' needs a command or cmd", proc.name).into());
+        }
+    }
+    let limit = config.max_processes.unwrap_or(DEFAULT_MAX_PROCESSES);
+    if config.processes.is_empty() || config.processes.len() > limit {
+        return Err(RunnerError::TooManyProcesses {
+            found: config.processes.len(),
+            limit,
+        });
+    }
+    if chrono::format::StrftimeItems::new(config.timestamp_format())
+        .parse()
+        .is_err()
+    {
+        return Err(format!("invalid timestamp_format '{}'", config.timestamp_format()).into());
+    }
+    #[cfg(feature = "tui")]
+    config.keybindings.check()?;
+    if config.usage_interval_ms.is_some_and(|ms| ms < 100) {
+        return Err("usage_interval_ms must be at least 100".into());
+    }
+    for (name, value) in [
+        ("output_capacity", config.output_capacity),
+        ("command_capacity", config.command_capacity),
+        ("max_queued_lines", config.max_queued_lines),
+    ] {
+        if value == Some(0) {
+            return Err(format!("{} must be at least 1", name).into());
+        }
+    }
+    if config.max_concurrent_starts == Some(0) {
+        return Err("max_concurrent_starts must be at least 1".into());
+    }
+    if config.nice.is_some_and(|n| !(-20..=19).contains(&n)) {
+        return Err("nice must be between -20 and 19".into());
+    }
+    if let Some(proc) = config
+        .processes
+        .iter()
+        .find(|p| !p.env_passthrough.is_empty() && !p.env_clear)
+    {
+        return Err(format!("env_passthrough of '{}' requires env_clear", proc.name).into());
+    }
+    if let Some(proc) = config.processes.iter().find(|p| p.max_attempts == Some(0)) {
+        return Err(format!("max_attempts of '{}' must be at least 1", proc.name).into());
+    }
+    if let Some(proc) = config
+        .processes
+        .iter()
+        .find(|p| p.ready_when.is_some() && p.ready_port.is_some())
+    {
+        return Err(format!("'{}' can't have both ready_when and ready_port", proc.name).into());
+    }
+    if config.on_ready.is_some() && !config.processes.iter().any(ProcessConfig::checks_ready) {
+        return Err("on_ready requires a process with ready_when or ready_port".into());
+    }
+    for proc in &config.processes {
+        // A `cwd` with variables is only known at start, where a bad file is reported.
+        if let Some(file) = &proc.env_file
+            && !proc.dir().to_string_lossy().contains('$')
+        {
+            read_env_file(&proc.dir().join(file))
+                .map_err(|e| format!("env_file of '{}': {}", proc.name, e))?;
+        }
+    }
+    check_references(&config.processes, "restart_with", |p| &p.restart_with)?;
+    check_references(&config.processes, "depends_on", |p| &p.depends_on)?;
+    if config.check_commands {
+        let problems = launch_problems(&config);
+        if !problems.is_empty() {
+            return Err(problems.join("; ").into());
+        }
     }
     Ok(config)
 }
+
+/// Returns what keeps each process from starting that can be told before
+/// running it: a missing working directory or command, as `name: problem`.
+pub fn launch_problems(config: &Config) -> Vec<String> {
+    config
+        .processes
+        .iter()
+        .filter_map(|p| p.check_launch().err().map(|e| format!("{}: {}", p.name, e)))
+        .collect()
+}
+
+/// Looks up an executable by name in the directories listed in `PATH`.
+pub fn find_in_path(program: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Reads the variables of an env file: `KEY=VALUE` lines, optionally preceded
+/// by `export`. Blank lines and lines starting with `#` are skipped. A value
+/// may be quoted: in double quotes `\n`, `\"` and `\\` are unescaped, in single
+/// quotes it is taken literally.
+pub fn read_env_file(path: &Path) -> Result<Vec<(String, String)>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("cannot read '{}': {}", path.display(), e))?;
+    let mut vars = Vec::new();
+    for (n, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line
+            .split_once('=')
+            .filter(|(key, _)| !key.trim().is_empty())
+            .ok_or_else(|| format!("{}:{}: expected KEY=VALUE", path.display(), n + 1))?;
+        vars.push((key.trim().to_string(), unquote(value.trim())));
+    }
+    Ok(vars)
+}
+
+/// Resolves the `cwd` of a process: a leading `~` is the home directory and a
+/// relative path is taken from `base`, the directory of the configuration file.
+/// A path starting with a variable is left for `expand_vars` at start.
+pub(crate) fn resolve_cwd(cwd: &str, base: &Path) -> String {
+    let cwd = expand_home(cwd);
+    if cwd.starts_with('$') || Path::new(&cwd).is_absolute() {
+        return cwd;
+    }
+    base.join(&cwd).to_string_lossy().into_owned()
+}
+
+/// Expands a leading `~` of a path to the home directory, from `HOME`, or
+/// `USERPROFILE` on Windows. Other paths are returned as they are.
+pub fn expand_home(path: &str) -> String {
+    let rest = match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => rest,
+        _ => return path.to_string(),
+    };
+    match std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+        Some(home) => format!("{}{}", home.to_string_lossy(), rest),
+        None => path.to_string(),
+    }
+}
+
+/// Expands the `${VAR}` and `$VAR` references in a value with `lookup`. `$$`
+/// stands for a literal `$`, as does a `$` not followed by a name or `{`.
+/// Unset variables expand to nothing, or are an error when `strict` is set.
+pub fn expand_vars(
+    value: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+    strict: bool,
+) -> Result<String, String> {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(pos) = rest.find('$') {
+        expanded.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        let (name, next) = if let Some(escaped) = after.strip_prefix('$') {
+            expanded.push('$');
+            rest = escaped;
+            continue;
+        } else if let Some(braced) = after.strip_prefix('{') {
+            let end = braced
+                .find('}')
+                .ok_or_else(|| format!("unterminated '${{' in '{}'", value))?;
+            (&braced[..end], &braced[end + 1..])
+        } else {
+            let end = after
+                .find(|c: char| !(c == '_' || c.is_ascii_alphanumeric()))
+                .unwrap_or(after.len());
+            if end == 0 || after.starts_with(|c: char| c.is_ascii_digit()) {
+                expanded.push('$');
+                rest = after;
+                continue;
+            }
+            (&after[..end], &after[end..])
+        };
+        match lookup(name) {
+            Some(var) => expanded.push_str(&var),
+            None if strict => return Err(format!("variable '{}' is not set", name)),
+            None => {}
+        }
+        rest = next;
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Splits a line into words at whitespace, like a shell: double quotes group
+/// words and allow `\` escapes, single quotes group words literally.
+pub fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '"' => {
+                let current = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next().ok_or("unterminated double quote")? {
+                        '"' => break,
+                        '\\' => current.push(chars.next().ok_or("unterminated double quote")?),
+                        c => current.push(c),
+                    }
+                }
+            }
+            '\'' => {
+                let current = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next().ok_or("unterminated single quote")? {
+                        '\'' => break,
+                        c => current.push(c),
+                    }
+                }
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Removes the quotes around an env file value, unescaping in double quotes.
+fn unquote(value: &str) -> String {
+    let quoted = |q: char| {
+        value
+            .strip_prefix(q)
+            .and_then(|v| v.strip_suffix(q))
+            .filter(|_| value.len() >= 2)
+    };
+    if let Some(literal) = quoted('\'') {
+        return literal.to_string();
+    }
+    let Some(escaped) = quoted('"') else {
+        return value.to_string();
+    };
+    let mut unescaped = String::with_capacity(escaped.len());
+    let mut chars = escaped.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some('n')) => unescaped.push('\n'),
+            ('\\', Some(&e @ ('"' | '\\'))) => unescaped.push(e),
+            _ => {
+                unescaped.push(c);
+                continue;
+            }
+        }
+        chars.next();
+    }
+    unescaped
+}
+
+/// Rewrites the configuration file with its processes listed in `order`, given
+/// by name. Everything else in the file is written back unchanged, except for
+/// comments and formatting.
+pub fn export_config(file_path: &str, order: &[&str]) -> Result<(), RunnerError> {
+    if Format::of(file_path) == Format::Toml {
+        let mut document: toml::Table = toml::from_str(&std::fs::read_to_string(file_path)?)?;
+        let processes = document
+            .get_mut("processes")
+            .and_then(toml::Value::as_array_mut)
+            .ok_or("configuration has no process list")?;
+        let position = |process: &toml::Value| {
+            let name = process.get("name").and_then(toml::Value::as_str);
+            order.iter().position(|n| Some(*n) == name)
+        };
+        processes.sort_by_key(|process| position(process).unwrap_or(order.len()));
+        std::fs::write(
+            file_path,
+            toml::to_string(&document).map_err(|e| e.to_string())?,
+        )?;
+        return Ok(());
+    }
+    let file = File::open(file_path)?;
+    let mut document: serde_yaml::Value = serde_yaml::from_reader(BufReader::new(file))?;
+    let processes = document
+        .get_mut("processes")
+        .and_then(serde_yaml::Value::as_sequence_mut)
+        .ok_or("configuration has no process list")?;
+    let position = |process: &serde_yaml::Value| {
+        let name = process.get("name").and_then(serde_yaml::Value::as_str);
+        order.iter().position(|n| Some(*n) == name)
+    };
+    processes.sort_by_key(|process| position(process).unwrap_or(order.len()));
+    let file = File::create(file_path)?;
+    serde_yaml::to_writer(BufWriter::new(file), &document)?;
+    Ok(())
+}
+
+impl Config {
+    /// Returns the `strftime` format of the output timestamps.
+    pub fn timestamp_format(&self) -> &str {
+        self.timestamp_format
+            .as_deref()
+            .unwrap_or(DEFAULT_TIMESTAMP_FORMAT)
+    }
+
+    /// Returns how often to sample the resource usage of the processes, if shown.
+    pub fn usage_interval(&self) -> Option<Duration> {
+        self.show_usage.then(|| {
+            Duration::from_millis(self.usage_interval_ms.unwrap_or(DEFAULT_USAGE_INTERVAL_MS))
+        })
+    }
+
+    /// Returns the capacity of the channel of output lines of each process.
+    pub fn output_capacity(&self) -> usize {
+        self.output_capacity.unwrap_or(DEFAULT_OUTPUT_CAPACITY)
+    }
+
+    /// Returns the capacity of the channel of commands of each process.
+    pub fn command_capacity(&self) -> usize {
+        self.command_capacity.unwrap_or(DEFAULT_COMMAND_CAPACITY)
+    }
+
+    /// Returns the indices of the processes listed in `depends_on` of the
+    /// process at `index`.
+    pub fn dependencies(&self, index: usize) -> Vec<usize> {
+        self.processes[index]
+            .depends_on
+            .iter()
+            .filter_map(|name| self.processes.iter().position(|p| p.name == *name))
+            .collect()
+    }
+
+    /// Returns the indices of all the processes, ordered so that each comes
+    /// after those in its `depends_on`, and otherwise in configuration order.
+    pub fn start_order(&self) -> Vec<usize> {
+        let mut remaining: Vec<usize> = (0..self.processes.len()).collect();
+        let mut order = Vec::with_capacity(remaining.len());
+        while !remaining.is_empty() {
+            // Cycles are rejected at load, so some process always has its dependencies placed.
+            let pos = remaining
+                .iter()
+                .position(|&i| self.dependencies(i).iter().all(|j| order.contains(j)))
+                .unwrap_or(0);
+            order.push(remaining.remove(pos));
+        }
+        order
+    }
+
+    /// Returns the indices of the processes to restart after the process at
+    /// `root` restarts: everything that lists it in `restart_with`, directly or
+    /// transitively, ordered so that each process comes after its dependencies.
+    pub fn restart_cascade(&self, root: usize) -> Vec<usize> {
+        let n = self.processes.len();
+        let depends = |i: usize, j: usize| {
+            self.processes[i]
+                .restart_with
+                .contains(&self.processes[j].name)
+        };
+        // Collect every transitive dependent of the root.
+        let mut members = vec![false; n];
+        let mut pending = vec![root];
+        while let Some(j) = pending.pop() {
+            for (i, member) in members.iter_mut().enumerate() {
+                if !*member && i != root && depends(i, j) {
+                    *member = true;
+                    pending.push(i);
+                }
+            }
+        }
+        // Emit members once all their in-cascade dependencies have been emitted.
+        let mut remaining: Vec<usize> = (0..n).filter(|&i| members[i]).collect();
+        let mut order = Vec::with_capacity(remaining.len());
+        while let Some(pos) = remaining
+            .iter()
+            .position(|&i| remaining.iter().all(|&j| !depends(i, j)))
+        {
+            order.push(remaining.remove(pos));
+        }
+        order
+    }
+}
+
+/// Checks that the process names listed in a field exist and don't form a cycle.
+///
+/// # Arguments
+/// * `processes` - The configured processes.
+/// * `field` - Name of the field, used in error messages.
+/// * `refs` - Returns the names listed in the field for a process.
+fn check_references<F>(processes: &[ProcessConfig], field: &str, refs: F) -> Result<(), RunnerError>
+where
+    F: Fn(&ProcessConfig) -> &[String],
+{
+    let index = |name: &str| processes.iter().position(|p| p.name == name);
+    for proc in processes {
+        for name in refs(proc) {
+            if index(name).is_none() {
+                return Err(format!(
+                    "{} of '{}' refers to unknown process '{}'",
+                    field, proc.name, name
+                )
+                .into());
+            }
+        }
+    }
+    // Depth-first search; a process seen again while still on the stack closes a cycle.
+    fn visit<F: Fn(&ProcessConfig) -> &[String]>(
+        i: usize,
+        processes: &[ProcessConfig],
+        refs: &F,
+        state: &mut [u8],
+        path: &mut Vec<usize>,
+    ) -> Option<Vec<usize>> {
+        state[i] = 1;
+        path.push(i);
+        for name in refs(&processes[i]) {
+            let j = processes.iter().position(|p| &p.name == name)?;
+            if state[j] == 1 {
+                let start = path.iter().position(|&k| k == j).unwrap();
+                let mut cycle = path[start..].to_vec();
+                cycle.push(j);
+                return Some(cycle);
+            }
+            if state[j] == 0
+                && let Some(cycle) = visit(j, processes, refs, state, path)
+            {
+                return Some(cycle);
+            }
+        }
+        path.pop();
+        state[i] = 2;
+        None
+    }
+    let mut state = vec![0u8; processes.len()];
+    for i in 0..processes.len() {
+        if state[i] == 0
+            && let Some(cycle) = visit(i, processes, &refs, &mut state, &mut Vec::new())
+        {
+            let names: Vec<&str> = cycle.iter().map(|&k| processes[k].name.as_str()).collect();
+            return Err(format!("{} forms a cycle: {}", field, names.join(" -> ")).into());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(yaml: &str) -> Config {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn restart_cascade_follows_dependents_in_order() {
+        let config = config(
+            "
+            processes:
+              - {name: web, cmd: 'true', restart_with: [api, db]}
+              - {name: worker, cmd: 'true', restart_with: [api]}
+              - {name: api, cmd: 'true', restart_with: [db]}
+              - {name: other, cmd: 'true'}
+              - {name: db, cmd: 'true'}
+            ",
+        );
+        assert_eq!(config.restart_cascade(4), [2, 0, 1]);
+        assert_eq!(config.restart_cascade(2), [0, 1]);
+        assert!(config.restart_cascade(3).is_empty());
+    }
+
+    #[test]
+    fn title_format_fills_in_the_placeholders() {
+        let format =
+            TitleFormat::parse("{name} {{{state}}} up {uptime}, {restarts} restarts").unwrap();
+        let title = format.render(|field| match field {
+            TitleField::Name => "api".to_string(),
+            TitleField::State => "running".to_string(),
+            TitleField::Uptime => "2m13s".to_string(),
+            TitleField::Restarts => "1".to_string(),
+            TitleField::Exit | TitleField::Health => unreachable!(),
+        });
+        assert_eq!(title, "api {running} up 2m13s, 1 restarts");
+    }
+
+    #[test]
+    fn title_format_rejects_malformed_templates() {
+        let error = |template: &str| TitleFormat::parse(template).unwrap_err();
+        assert_eq!(error("{name"), "unterminated placeholder");
+        assert_eq!(error("{pid}"), "unknown placeholder '{pid}'");
+        assert_eq!(error("name}"), "unmatched '}'");
+    }
+
+    #[test]
+    fn export_rewrites_the_processes_in_the_given_order() {
+        let path = std::env::temp_dir().join(format!("starter-{}-export.yaml", std::process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::write(
+            path,
+            "layout: grid\nprocesses:\n  - {name: api, cmd: 'true'}\n  - {name: db, cmd: 'true'}\n",
+        )
+        .unwrap();
+        export_config(path, &["db", "api"]).unwrap();
+        let config = config(&std::fs::read_to_string(path).unwrap());
+        let names: Vec<&str> = config.processes.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["db", "api"]);
+        std::fs::write(path, "layout: grid\n").unwrap();
+        let error = export_config(path, &["db"]).unwrap_err();
+        std::fs::remove_file(path).unwrap();
+        assert!(matches!(error, RunnerError::InvalidConfig(_)));
+    }
+
+    #[cfg(feature = "tui")]
+    #[test]
+    fn actions_can_take_over_built_in_keys_but_not_each_other() {
+        use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+        let bound = config("processes: []\nkeybindings: {clear: S, quit: ctrl+q}");
+        assert!(bound.keybindings.check().is_ok());
+        let press = |code| {
+            bound
+                .keybindings
+                .translate(KeyEvent::new(code, KeyModifiers::NONE))
+        };
+        assert_eq!(press(KeyCode::Char('S')).code, KeyCode::Char('c'));
+        assert_eq!(press(KeyCode::Char('c')).code, KeyCode::Null);
+        assert_eq!(press(KeyCode::Char('/')).code, KeyCode::Char('/'));
+        let clash = config("processes: []\nkeybindings: {clear: x, restart: x}");
+        assert_eq!(
+            clash.keybindings.check().unwrap_err(),
+            "keybindings: 'x' is bound to both restart and clear"
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use super::Pattern;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::style::Color;
+use serde::{Deserialize, Deserializer};
+
+/// Coloring of the output lines by their contents. Colors from ANSI escape
+/// sequences win over these.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Highlight {
+    /// Color lines by their log level; on unless set to `false`.
+    #[serde(default)]
+    pub levels: Option<bool>,
+    /// Words or patterns colored wherever they appear.
+    #[serde(default)]
+    pub keywords: Vec<Keyword>,
+}
+
+/// A pattern colored in the output, for `highlight`.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Keyword {
+    pub pattern: Pattern,
+    pub color: ColorName,
+    #[serde(default)]
+    pub bold: bool,
+}
+
+/// A color from the configuration: a name such as `red` or `lightblue`, an
+/// index of the 256-color palette or a `#rrggbb` value.
+#[derive(Debug, Clone, Copy)]
+pub struct ColorName(pub Color);
+
+impl<'de> Deserialize<'de> for ColorName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse()
+            .map(ColorName)
+            .map_err(|_| serde::de::Error::custom(format!("unknown color '{}'", name)))
+    }
+}
+
+/// A key with its modifiers, written like `q`, `space`, `pagedown`, `f5` or
+/// `ctrl+c`, with `ctrl`, `alt` and `shift` as modifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl Key {
+    const fn new(code: KeyCode) -> Self {
+        Key {
+            code,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+
+    /// Whether a key press is this key. Shift is left out for characters, as
+    /// terminals report it along with the uppercase letter or the symbol.
+    fn matches(&self, event: &KeyEvent) -> bool {
+        let ignored = match event.code {
+            KeyCode::Char(_) => KeyModifiers::SHIFT,
+            _ => KeyModifiers::NONE,
+        };
+        event.code == self.code
+            && event.modifiers.difference(ignored) == self.modifiers.difference(ignored)
+    }
+}
+
+impl std::str::FromStr for Key {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, String> {
+        let mut parts: Vec<&str> = text.split('+').collect();
+        // A trailing `+` is the plus key itself, as in `ctrl++`.
+        let name = match parts.pop() {
+            Some("") if text.ends_with('+') => {
+                parts.pop();
+                "+"
+            }
+            Some(name) => name,
+            None => "",
+        };
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in parts {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier '{}' in key '{}'", modifier, text)),
+            };
+        }
+        let mut chars = name.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match name.to_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" | "ins" => KeyCode::Insert,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "pageup" | "pgup" => KeyCode::PageUp,
+                "pagedown" | "pgdn" => KeyCode::PageDown,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                lower => match lower.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => return Err(format!("unknown key '{}'", text)),
+                },
+            },
+        };
+        Ok(Key { code, modifiers })
+    }
+}
+
+impl std::fmt::Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "Ctrl+"),
+            (KeyModifiers::ALT, "Alt+"),
+            (KeyModifiers::SHIFT, "Shift+"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("<Space>"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::Up => f.write_str("↑"),
+            KeyCode::Down => f.write_str("↓"),
+            KeyCode::PageUp => f.write_str("PgUp"),
+            KeyCode::PageDown => f.write_str("PgDn"),
+            code => write!(f, "{}", code),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Key {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Keys of the actions that can be bound to others, in the `keybindings`
+/// section. Those left out keep their default key. A key the TUI handles
+/// itself, such as `S`, `/` or a pane number, can be bound too: it then runs
+/// the action instead of its own.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Keybindings {
+    pub quit: Key,
+    pub toggle: Key,
+    pub restart: Key,
+    pub clear: Key,
+    pub scroll_up: Key,
+    pub scroll_down: Key,
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Keybindings {
+            quit: Key::new(KeyCode::Char('q')),
+            toggle: Key::new(KeyCode::Char(' ')),
+            restart: Key::new(KeyCode::Char('r')),
+            clear: Key::new(KeyCode::Char('c')),
+            scroll_up: Key::new(KeyCode::Up),
+            scroll_down: Key::new(KeyCode::Down),
+        }
+    }
+}
+
+impl Keybindings {
+    /// The actions with their keys, paired with their default keys.
+    fn bindings(&self) -> [(&'static str, Key, Key); 6] {
+        let defaults = Keybindings::default();
+        [
+            ("quit", self.quit, defaults.quit),
+            ("toggle", self.toggle, defaults.toggle),
+            ("restart", self.restart, defaults.restart),
+            ("clear", self.clear, defaults.clear),
+            ("scroll_up", self.scroll_up, defaults.scroll_up),
+            ("scroll_down", self.scroll_down, defaults.scroll_down),
+        ]
+    }
+
+    /// Turns a key press into the one the TUI handles: the default key of the
+    /// action it is bound to, or nothing for the default key of an action
+    /// bound elsewhere. Other keys are left as they are.
+    pub fn translate(&self, event: KeyEvent) -> KeyEvent {
+        let bindings = self.bindings();
+        if let Some((_, _, default)) = bindings.iter().find(|(_, key, _)| key.matches(&event)) {
+            return KeyEvent::new(default.code, default.modifiers);
+        }
+        if bindings
+            .iter()
+            .any(|(_, key, default)| key != default && default.matches(&event))
+        {
+            return KeyEvent::new(KeyCode::Null, KeyModifiers::NONE);
+        }
+        event
+    }
+
+    /// Fails if two actions are bound to the same key. Binding one to a key
+    /// with a meaning of its own is allowed, as it overrides that meaning.
+    pub(super) fn check(&self) -> Result<(), String> {
+        let bindings = self.bindings();
+        for (i, (action, key, _)) in bindings.iter().enumerate() {
+            if let Some((other, _, _)) = bindings[..i].iter().find(|(_, k, _)| k == key) {
+                return Err(format!(
+                    "keybindings: '{}' is bound to both {} and {}",
+                    key, other, action
+                ));
+            }
+        }
+        Ok(())
+    }
+}
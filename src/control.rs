@@ -0,0 +1,115 @@
+use crate::config::split_words;
+use crate::process::{CommandUpdate, ProcessCommand};
+use tokio::sync::{mpsc::Sender, oneshot};
+
+/// A request read from the control socket.
+enum Request {
+    /// Change the command line of a process: `reload NAME key=value...`,
+    /// with `command`, `args` and `cwd` as keys.
+    Reload { name: String, update: CommandUpdate },
+}
+
+/// Listens on a Unix socket for control commands, one per line, answering each
+/// with `ok` or `error: <reason>`. A stale socket file is replaced.
+///
+/// # Arguments
+/// * `path` - Where to create the socket.
+/// * `names` - The process names, in configuration order.
+/// * `senders` - The control senders of the processes, in the same order.
+#[cfg(unix)]
+pub fn spawn_control_socket(
+    path: &str,
+    names: Vec<String>,
+    senders: Vec<Sender<ProcessCommand>>,
+) -> std::io::Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::UnixListener;
+    let _ = std::fs::remove_file(path);
+    let listener = UnixListener::bind(path)?;
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let names = names.clone();
+            let senders = senders.clone();
+            tokio::spawn(async move {
+                let (reader, mut writer) = stream.into_split();
+                let mut lines = BufReader::new(reader).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let answer = match handle_request(&line, &names, &senders).await {
+                        Ok(()) => "ok\n".to_string(),
+                        Err(e) => format!("error: {}\n", e),
+                    };
+                    if writer.write_all(answer.as_bytes()).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn spawn_control_socket(
+    _path: &str,
+    _names: Vec<String>,
+    _senders: Vec<Sender<ProcessCommand>>,
+) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "control_socket is only supported on Unix",
+    ))
+}
+
+/// Parses a request line and forwards it to the process it names.
+async fn handle_request(
+    line: &str,
+    names: &[String],
+    senders: &[Sender<ProcessCommand>],
+) -> Result<(), String> {
+    match parse_request(line)? {
+        Request::Reload { name, update } => {
+            let index = names
+                .iter()
+                .position(|n| *n == name)
+                .ok_or_else(|| format!("unknown process '{}'", name))?;
+            let (reply, outcome) = oneshot::channel();
+            senders[index]
+                .send(ProcessCommand::Reload { update, reply })
+                .await
+                .map_err(|_| "process is no longer supervised".to_string())?;
+            outcome
+                .await
+                .map_err(|_| "process is no longer supervised".to_string())?
+        }
+    }
+}
+
+fn parse_request(line: &str) -> Result<Request, String> {
+    let words = split_words(line)?;
+    match words.first().map(String::as_str) {
+        Some("reload") => {
+            let name = words.get(1).ok_or("usage: reload NAME key=value...")?;
+            let mut update = CommandUpdate::default();
+            for setting in &words[2..] {
+                let (key, value) = setting
+                    .split_once('=')
+                    .ok_or_else(|| format!("expected key=value, got '{}'", setting))?;
+                match key {
+                    "command" => update.command = Some(value.to_string()),
+                    "args" => update.args = Some(split_words(value)?),
+                    "cwd" => update.cwd = Some(value.to_string()),
+                    _ => return Err(format!("unknown setting '{}'", key)),
+                }
+            }
+            Ok(Request::Reload {
+                name: name.clone(),
+                update,
+            })
+        }
+        Some(other) => Err(format!("unknown command '{}'", other)),
+        None => Err("empty request".to_string()),
+    }
+}
@@ -0,0 +1,105 @@
+use std::ops::Range;
+
+/// Splits a line into tokens: runs of alphanumeric characters, `_`, `.` and `-`,
+/// so that words and numbers compare as a whole, and single other characters.
+fn tokens(line: &str) -> Vec<Range<usize>> {
+    let is_word = |c: char| c.is_alphanumeric() || matches!(c, '_' | '.' | '-');
+    let mut tokens: Vec<Range<usize>> = Vec::new();
+    for (i, c) in line.char_indices() {
+        let end = i + c.len_utf8();
+        match tokens.last_mut() {
+            Some(last) if is_word(c) && line[last.clone()].chars().all(is_word) => last.end = end,
+            _ => tokens.push(i..end),
+        }
+    }
+    tokens
+}
+
+/// Returns the byte ranges of `current` that differ from `previous`, for lines
+/// repeating the same structure with changing values, such as state dumps.
+///
+/// Lines with the same number of tokens are compared token by token. Otherwise
+/// the part between their common prefix and suffix is reported. Returns `None`
+/// when the lines have nothing in common, so that diffing is not applicable.
+pub fn changed_ranges(previous: &str, current: &str) -> Option<Vec<Range<usize>>> {
+    let (old, new) = (tokens(previous), tokens(current));
+    let mut changes: Vec<Range<usize>> = Vec::new();
+    if old.len() == new.len() {
+        for (a, b) in old.into_iter().zip(new) {
+            if previous[a] == current[b.clone()] {
+                continue;
+            }
+            match changes.last_mut() {
+                Some(last) if last.end == b.start => last.end = b.end,
+                _ => changes.push(b),
+            }
+        }
+    } else {
+        let prefix: usize = previous
+            .chars()
+            .zip(current.chars())
+            .take_while(|(a, b)| a == b)
+            .map(|(_, b)| b.len_utf8())
+            .sum();
+        let suffix: usize = previous[prefix..]
+            .chars()
+            .rev()
+            .zip(current[prefix..].chars().rev())
+            .take_while(|(a, b)| a == b)
+            .map(|(_, b)| b.len_utf8())
+            .sum();
+        let (start, end) = (prefix, current.len() - suffix);
+        if start < end {
+            changes.push(start..end);
+        }
+    }
+    let changed: usize = changes.iter().map(|r| r.len()).sum();
+    if !current.is_empty() && changed == current.len() {
+        return None;
+    }
+    Some(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The changed parts of `current`, as text.
+    fn changed(previous: &str, current: &'static str) -> Option<Vec<&'static str>> {
+        let changes = changed_ranges(previous, current)?;
+        Some(changes.into_iter().map(|range| &current[range]).collect())
+    }
+
+    #[test]
+    fn changed_tokens_are_reported_and_adjacent_ones_merged() {
+        assert_eq!(
+            changed_ranges("cpu=10 mem=200", "cpu=12 mem=300"),
+            Some(vec![4..6, 11..14])
+        );
+        assert_eq!(
+            changed("cpu=10 mem=200", "cpu=12 mem=200"),
+            Some(vec!["12"])
+        );
+        assert_eq!(changed("x 1+", "x 2*"), Some(vec!["2*"]));
+        assert_eq!(changed("same", "same"), Some(vec![]));
+    }
+
+    #[test]
+    fn multibyte_text_gives_byte_ranges() {
+        assert_eq!(changed("état=prêt", "état=arrêté"), Some(vec!["arrêté"]));
+        assert_eq!(changed("temp 20°C", "temp 21°C"), Some(vec!["21"]));
+    }
+
+    #[test]
+    fn lines_with_different_token_counts_report_the_middle() {
+        assert_eq!(changed("status: ok", "status: not ok"), Some(vec!["not "]));
+        assert_eq!(changed("→ a", "→ b c"), Some(vec!["b c"]));
+        assert_eq!(changed("a b", "a b c d"), Some(vec![" c d"]));
+    }
+
+    #[test]
+    fn lines_with_nothing_in_common_are_not_diffed() {
+        assert_eq!(changed_ranges("abc", "xyz"), None);
+        assert_eq!(changed_ranges("a b", "cdef"), None);
+    }
+}
@@ -0,0 +1,77 @@
+use std::io;
+use thiserror::Error;
+
+/// Ways the runner can fail, from loading the configuration to running the TUI.
+#[derive(Debug, Error)]
+pub enum RunnerError {
+    /// The configuration file couldn't be read.
+    #[error("cannot read the file: {0}")]
+    ConfigNotFound(io::Error),
+    /// The configuration file isn't valid YAML or TOML, or doesn't describe a
+    /// configuration.
+    #[error("{0}")]
+    ConfigParse(String),
+    /// There are no processes, or more than `max_processes`.
+    #[error("Number of processes must be between 1 and {limit} (max_processes), found {found}")]
+    TooManyProcesses { found: usize, limit: usize },
+    /// A setting of the configuration is invalid.
+    #[error("{0}")]
+    InvalidConfig(String),
+    /// None of the processes could be started, with the error of each one.
+    #[error("no process could be started:{}", list_failures(.0))]
+    SpawnFailed(Vec<(String, String)>),
+    /// The `control_socket` couldn't be created.
+    #[error("cannot open the control socket: {0}")]
+    ControlSocket(io::Error),
+    /// A file given on the command line couldn't be opened.
+    #[error("cannot open '{path}': {source}")]
+    Open { path: String, source: io::Error },
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl RunnerError {
+    /// Whether the error is about the configuration file, so that it is
+    /// reported along with the path of the file.
+    pub fn is_config(&self) -> bool {
+        matches!(
+            self,
+            RunnerError::ConfigNotFound(_)
+                | RunnerError::ConfigParse(_)
+                | RunnerError::TooManyProcesses { .. }
+                | RunnerError::InvalidConfig(_)
+        )
+    }
+}
+
+/// Lists each process and its error on a line of its own.
+fn list_failures(failures: &[(String, String)]) -> String {
+    failures
+        .iter()
+        .map(|(name, error)| format!("\n  {}: {}", name, error))
+        .collect()
+}
+
+impl From<serde_yaml::Error> for RunnerError {
+    fn from(e: serde_yaml::Error) -> Self {
+        RunnerError::ConfigParse(e.to_string())
+    }
+}
+
+impl From<toml::de::Error> for RunnerError {
+    fn from(e: toml::de::Error) -> Self {
+        RunnerError::ConfigParse(e.to_string())
+    }
+}
+
+impl From<String> for RunnerError {
+    fn from(message: String) -> Self {
+        RunnerError::InvalidConfig(message)
+    }
+}
+
+impl From<&str> for RunnerError {
+    fn from(message: &str) -> Self {
+        RunnerError::InvalidConfig(message.to_string())
+    }
+}
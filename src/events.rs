@@ -0,0 +1,89 @@
+use crate::line_file::LineFile;
+use crate::process::{ExitReason, OutputLine, Stream};
+use crate::stats::Event;
+use serde::Serialize;
+use std::io;
+
+/// One line of the event stream. Fields that don't apply to the event are left out.
+#[derive(Serialize)]
+struct Record<'a> {
+    time: String,
+    process: &'a str,
+    event: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signal: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ready_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<&'a str>,
+}
+
+impl<'a> Record<'a> {
+    fn new(process: &'a str, event: &'static str) -> Self {
+        Record {
+            time: chrono::Local::now()
+                .format("%Y-%m-%dT%H:%M:%S%.3f%:z")
+                .to_string(),
+            process,
+            event,
+            code: None,
+            signal: None,
+            ready_ms: None,
+            stream: None,
+            text: None,
+        }
+    }
+}
+
+/// Writes a JSON object per line for every lifecycle event and output line of
+/// the processes, for `--events`.
+///
+/// Each object has the local time with milliseconds, the process name and the
+/// event: `start`, `failed`, `ready` with `ready_ms`, `ready_timeout`, `restart`,
+/// `stop`, `exit` with the exit `code` or the `signal` name when known, or
+/// `output` with the `stream` and the `text` of the line.
+pub struct EventStream {
+    file: LineFile,
+}
+
+impl EventStream {
+    /// Opens the file for appending, creating it if needed. A named pipe works too.
+    pub fn create(path: &str) -> io::Result<Self> {
+        Ok(EventStream {
+            file: LineFile::open(path)?,
+        })
+    }
+
+    /// Writes a lifecycle event of the named process.
+    pub fn record(&self, process: &str, event: &Event) {
+        let mut record = Record::new(process, event.name());
+        match event {
+            Event::Ready(latency) => record.ready_ms = Some(latency.as_millis() as u64),
+            Event::Exit(Some(ExitReason::Code(code))) => record.code = Some(*code),
+            Event::Exit(Some(exit)) => record.signal = Some(exit.to_string()),
+            _ => {}
+        }
+        self.write(&record);
+    }
+
+    /// Writes a line of output of the named process.
+    pub fn output(&self, process: &str, line: &OutputLine) {
+        let mut record = Record::new(process, "output");
+        record.stream = Some(match line.stream {
+            Stream::Stdout => "stdout",
+            Stream::Stderr => "stderr",
+        });
+        record.text = Some(&line.text);
+        self.write(&record);
+    }
+
+    fn write(&self, record: &Record) {
+        if let Ok(line) = serde_json::to_string(record) {
+            self.file.write_line(&line);
+        }
+    }
+}
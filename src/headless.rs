@@ -0,0 +1,118 @@
+use crate::process::{
+    ExitReason, OutputChannels, OutputLine, ProcessCommand, ProcessState, ProcessStatus, Stream,
+};
+use std::{io::Write, time::Duration};
+use tokio::sync::mpsc;
+
+/// How often the processes are checked for having finished.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Once every process is done, output still on its way is printed until none
+/// has come for this long.
+const DRAIN_QUIET: Duration = Duration::from_millis(100);
+
+/// Why the latest run of a process counts as a failure, if it does.
+fn failure(status: &ProcessStatus) -> Option<String> {
+    match (status.state, status.exit) {
+        (ProcessState::Failed, _) => Some("failed to start".to_string()),
+        (ProcessState::Exited, Some(ExitReason::Code(0))) => None,
+        (ProcessState::Exited, Some(ExitReason::Code(code))) => {
+            Some(format!("exited with code {}", code))
+        }
+        (ProcessState::Exited, Some(signal)) => Some(format!("was killed by {}", signal)),
+        _ if status.starts == 0 && !status.waiting_for.is_empty() => Some(format!(
+            "never started, waiting for {}",
+            status.waiting_for.join(", ")
+        )),
+        _ => None,
+    }
+}
+
+/// Prints a line of output with the name of its process, padded to `width`:
+/// stdout lines to stdout and stderr lines, including those of the runner, to
+/// stderr.
+fn print_line(name: &str, width: usize, line: &OutputLine) {
+    let text = format!(
+        "[{}]{:pad$} {}\n",
+        name,
+        "",
+        line.text,
+        pad = width - name.len()
+    );
+    let _ = match line.stream {
+        Stream::Stdout => std::io::stdout().write_all(text.as_bytes()),
+        Stream::Stderr => std::io::stderr().write_all(text.as_bytes()),
+    };
+}
+
+/// Runs the processes without the TUI, for `--no-tui`: starts those started automatically and
+/// prints their output, each line prefixed with the process name, until every
+/// process is done and none will start again. With `fail_fast`, the run ends
+/// as soon as a process fails instead. Ctrl+C ends it too, as a failure.
+///
+/// Returns whether the run succeeded: no process exited with a nonzero code,
+/// was killed, failed to start or was left waiting for its dependencies. The
+/// reason of each failure is printed to stderr.
+pub async fn run_headless(channels: OutputChannels, fail_fast: bool) -> bool {
+    let width = channels.iter().map(|h| h.name.len()).max().unwrap_or(0);
+    let (tx, mut rx) = mpsc::unbounded_channel::<(String, OutputLine)>();
+    let mut processes = Vec::new();
+    for mut handle in channels {
+        if !handle.status.lock().unwrap().waits_for_user() {
+            let _ = handle.control.try_send(ProcessCommand::Start);
+        }
+        processes.push((handle.name.clone(), handle.status.clone()));
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            while let Some(line) = handle.output.recv().await {
+                if tx.send((handle.name.clone(), line)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    let mut poll = tokio::time::interval(POLL_INTERVAL);
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    let (interrupted, finished) = loop {
+        tokio::select! {
+            Some((name, line)) = rx.recv() => print_line(&name, width, &line),
+            _ = poll.tick() => {
+                let statuses: Vec<ProcessStatus> = processes
+                    .iter()
+                    .map(|(_, status)| status.lock().unwrap().clone())
+                    .collect();
+                if statuses.iter().all(ProcessStatus::is_settled) {
+                    break (false, true);
+                }
+                if fail_fast && statuses.iter().any(|s| failure(s).is_some()) {
+                    break (false, false);
+                }
+            }
+            _ = &mut ctrl_c => break (true, false),
+        }
+    };
+    if finished {
+        while let Ok(Some((name, line))) = tokio::time::timeout(DRAIN_QUIET, rx.recv()).await {
+            print_line(&name, width, &line);
+        }
+    } else {
+        // Processes still running keep printing, so only what has come is shown.
+        for _ in 0..rx.len() {
+            if let Ok((name, line)) = rx.try_recv() {
+                print_line(&name, width, &line);
+            }
+        }
+    }
+    let mut success = !interrupted;
+    for (name, status) in &processes {
+        if let Some(reason) = failure(&status.lock().unwrap()) {
+            eprintln!("starter: {} {}", name, reason);
+            success = false;
+        }
+    }
+    if interrupted {
+        eprintln!("starter: interrupted");
+    }
+    success
+}
@@ -0,0 +1,22 @@
+/// Log level of an output line, as detected from its text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+/// Detects the severity of a line from common log-level tokens such as
+/// `ERROR`, `WARN` or `INFO`. Tokens are matched as whole words, ignoring case,
+/// and the first recognized one wins.
+pub fn detect_severity(line: &str) -> Option<Severity> {
+    line.split(|c: char| !c.is_ascii_alphanumeric())
+        .find_map(|token| match token.to_ascii_uppercase().as_str() {
+            "ERROR" | "ERR" | "FATAL" | "CRITICAL" | "PANIC" => Some(Severity::Error),
+            "WARN" | "WARNING" => Some(Severity::Warn),
+            "INFO" => Some(Severity::Info),
+            "DEBUG" | "TRACE" => Some(Severity::Debug),
+            _ => None,
+        })
+}
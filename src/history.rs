@@ -0,0 +1,121 @@
+use std::{fs, io};
+
+/// Number of entries kept in a history.
+const CAPACITY: usize = 100;
+
+/// Recently entered prompt inputs, newest last, browsed with the up and down
+/// keys. An entry repeating the newest one is not added again.
+#[derive(Default)]
+pub struct History {
+    entries: Vec<String>,
+    /// Entry being shown while browsing, counted from the oldest.
+    cursor: Option<usize>,
+}
+
+impl History {
+    /// Reads a history saved with `save`, one entry per line. A missing or
+    /// unreadable file gives an empty history.
+    pub fn load(path: &str) -> Self {
+        let entries: Vec<String> = fs::read_to_string(path)
+            .map(|contents| contents.lines().map(String::from).collect())
+            .unwrap_or_default();
+        let skip = entries.len().saturating_sub(CAPACITY);
+        History {
+            entries: entries.into_iter().skip(skip).collect(),
+            cursor: None,
+        }
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let contents: String = self.entries.iter().map(|e| format!("{}\n", e)).collect();
+        fs::write(path, contents)
+    }
+
+    /// Adds an entry, dropping the oldest one past the capacity, and ends browsing.
+    pub fn push(&mut self, entry: &str) {
+        self.cursor = None;
+        if entry.is_empty() || self.entries.last().is_some_and(|last| last == entry) {
+            return;
+        }
+        if self.entries.len() == CAPACITY {
+            self.entries.remove(0);
+        }
+        self.entries.push(entry.to_string());
+    }
+
+    /// Ends browsing, so that the next step starts again from the newest entry.
+    pub fn rewind(&mut self) {
+        self.cursor = None;
+    }
+
+    /// Steps to the next older entry and returns it, staying at the oldest one.
+    pub fn older(&mut self) -> Option<&str> {
+        let index = match self.cursor {
+            None => self.entries.len().checked_sub(1)?,
+            Some(i) => i.saturating_sub(1),
+        };
+        self.cursor = Some(index);
+        Some(&self.entries[index])
+    }
+
+    /// Steps to the next newer entry and returns it, or `None` once past the
+    /// newest one, where browsing ends.
+    pub fn newer(&mut self) -> Option<&str> {
+        let index = self.cursor? + 1;
+        if index >= self.entries.len() {
+            self.cursor = None;
+            return None;
+        }
+        self.cursor = Some(index);
+        Some(&self.entries[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(entries: &[&str]) -> History {
+        let mut history = History::default();
+        for entry in entries {
+            history.push(entry);
+        }
+        history
+    }
+
+    #[test]
+    fn browsing_goes_back_and_forth_between_the_entries() {
+        let mut history = history(&["make", "cargo test", "ls"]);
+        assert_eq!(history.newer(), None);
+        assert_eq!(history.older(), Some("ls"));
+        assert_eq!(history.older(), Some("cargo test"));
+        assert_eq!(history.older(), Some("make"));
+        assert_eq!(history.older(), Some("make"));
+        assert_eq!(history.newer(), Some("cargo test"));
+        assert_eq!(history.newer(), Some("ls"));
+        assert_eq!(history.newer(), None);
+        assert_eq!(history.older(), Some("ls"));
+        history.rewind();
+        assert_eq!(history.older(), Some("ls"));
+        assert_eq!(History::default().older(), None);
+    }
+
+    #[test]
+    fn repeated_and_empty_entries_are_not_added() {
+        let mut history = history(&["ls", "ls", "", "make", "ls"]);
+        assert_eq!(history.entries, ["ls", "make", "ls"]);
+        history.older();
+        history.push("make");
+        assert_eq!(history.older(), Some("make"));
+    }
+
+    #[test]
+    fn the_oldest_entries_are_dropped_past_the_capacity() {
+        let mut history = History::default();
+        for i in 0..CAPACITY + 5 {
+            history.push(&i.to_string());
+        }
+        assert_eq!(history.entries.len(), CAPACITY);
+        assert_eq!(history.entries[0], "5");
+    }
+}
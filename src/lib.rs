@@ -0,0 +1,45 @@
+//! Runs a set of commands described in a configuration file and supervises
+//! them: starts and stops, restarts, readiness checks and dependencies, with
+//! their output delivered line by line over channels.
+//!
+//! [`load_config`] reads a configuration and [`spawn_process`] sets up a
+//! supervisor per process, returning a [`ProcessHandle`] per process, to start
+//! and stop it, read its output and watch its status, and the
+//! [`ProcessManager`] of them all. The TUI, behind the `tui` feature, is one
+//! front end on top of them; [`headless::run_headless`] is another.
+
+pub mod ansi;
+mod cgroup;
+#[cfg(feature = "tui")]
+mod clipboard;
+pub mod config;
+mod control;
+#[cfg(feature = "tui")]
+mod diff;
+pub mod error;
+pub mod events;
+pub mod headless;
+#[cfg(feature = "tui")]
+mod highlight;
+#[cfg(feature = "tui")]
+mod history;
+mod line_file;
+pub mod orphans;
+pub mod process;
+pub mod session_log;
+#[cfg(feature = "tui")]
+mod snapshot;
+pub mod stats;
+#[cfg(feature = "tui")]
+mod structured;
+pub mod summary;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod usage;
+
+pub use config::{Config, ProcessConfig, load_config};
+pub use error::RunnerError;
+pub use process::{
+    OutputChannels, OutputLine, ProcessCommand, ProcessHandle, ProcessManager, ProcessStatus,
+    Stream, spawn_process,
+};
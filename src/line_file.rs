@@ -0,0 +1,58 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    sync::Mutex,
+};
+
+/// A file the runner appends lines to from every process, such as the stats
+/// file, the event stream and the session log.
+///
+/// Lines are written straight to the file, each in a single write, so the
+/// file is complete even if the runner is killed and a reader on a pipe sees
+/// every line as it comes.
+pub struct LineFile {
+    file: Mutex<File>,
+}
+
+impl LineFile {
+    /// Opens the file for appending, creating it if needed. A named pipe works too.
+    pub fn open(path: &str) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(LineFile {
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Whether nothing was written to the file yet.
+    pub fn is_empty(&self) -> io::Result<bool> {
+        Ok(self.file.lock().unwrap().metadata()?.len() == 0)
+    }
+
+    /// Appends a line, ending it with a line feed. Write errors are ignored, so
+    /// a full disk or a reader going away doesn't affect the processes.
+    pub fn write_line(&self, text: &str) {
+        let line = format!("{}\n", text);
+        let _ = self.file.lock().unwrap().write_all(line.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_are_appended_to_what_the_file_had() {
+        let path = std::env::temp_dir().join(format!("starter-{}-lines", std::process::id()));
+        let path = path.to_str().unwrap();
+        let file = LineFile::open(path).unwrap();
+        assert!(file.is_empty().unwrap());
+        file.write_line("first");
+        drop(file);
+        let file = LineFile::open(path).unwrap();
+        assert!(!file.is_empty().unwrap());
+        file.write_line("second");
+        let contents = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(contents, "first\nsecond\n");
+    }
+}
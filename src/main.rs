@@ -1,18 +1,126 @@
-mod config;
-mod process;
-mod tui;
+use clap::Parser;
+use starter::config::launch_problems;
+use starter::tui::run_tui;
+use starter::{
+    RunnerError, events, headless, load_config, orphans, process, session_log, spawn_process,
+    stats, summary,
+};
 
-use config::load_config;
-use process::spawn_process;
-use tui::run_tui;
+/// Run and control commands in parallel.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Configuration file describing the processes to run.
+    #[arg(default_value = "config.yaml")]
+    config: String,
+    /// Kill process groups left over by a crashed run without asking (needs `state_file`).
+    #[arg(long)]
+    kill_orphans: bool,
+    /// Append a CSV row per process start, stop, exit, restart and readiness to this file.
+    #[arg(long, value_name = "PATH")]
+    stats_csv: Option<String>,
+    /// Append a JSON object per process lifecycle event and output line to this file.
+    #[arg(long, value_name = "PATH")]
+    events: Option<String>,
+    /// Append the output lines and lifecycle events of all the processes to
+    /// this file, in the order they arrive, each with its time and process name.
+    #[arg(long, value_name = "PATH")]
+    log_all: Option<String>,
+    /// Print the output of the processes, prefixed with their names, instead of
+    /// showing the TUI, and exit once they are all done.
+    #[arg(long)]
+    no_tui: bool,
+    /// With --no-tui, stop everything as soon as a process fails.
+    #[arg(long, requires = "no_tui")]
+    fail_fast: bool,
+    /// Check the configuration, including that every working directory and
+    /// command can be found, and exit without starting anything.
+    #[arg(long)]
+    check: bool,
+}
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = std::env::args().collect();
-    let config_file = args.get(1).map(|s| s.as_str()).unwrap_or("config.yaml");
-    let config = load_config(config_file).expect("Failed to load config");
-    let (channels, mut manager) = spawn_process(&config).await?;
-    run_tui(channels).await?;
+async fn main() {
+    let cli = Cli::parse();
+    match run(&cli).await {
+        Ok(0) => {}
+        Ok(code) => std::process::exit(code),
+        Err(e) => {
+            if e.is_config() {
+                eprintln!("starter: {}: {}", cli.config, e);
+            } else {
+                eprintln!("starter: {}", e);
+            }
+            std::process::exit(process::EXIT_FAILURE);
+        }
+    }
+}
+
+/// Runs the session asked for on the command line and returns the exit code.
+async fn run(cli: &Cli) -> Result<i32, RunnerError> {
+    let config = load_config(&cli.config)?;
+    if cli.check {
+        let problems = launch_problems(&config);
+        for problem in &problems {
+            eprintln!("starter: {}: {}", cli.config, problem);
+        }
+        if !problems.is_empty() {
+            return Ok(process::EXIT_FAILURE);
+        }
+        println!(
+            "{}: {} processes, no problems found",
+            cli.config,
+            config.processes.len()
+        );
+        return Ok(0);
+    }
+    if let Some(path) = &config.state_file {
+        orphans::cleanup_orphans(path, cli.kill_orphans)?;
+    }
+    let session_start = std::time::Instant::now();
+    let stats = cli
+        .stats_csv
+        .as_deref()
+        .map(|path| stats::StatsFile::create(path).map_err(|source| open_error(path, source)))
+        .transpose()?;
+    let events = cli
+        .events
+        .as_deref()
+        .map(|path| events::EventStream::create(path).map_err(|source| open_error(path, source)))
+        .transpose()?;
+    let session_log = cli
+        .log_all
+        .as_deref()
+        .map(|path| {
+            session_log::SessionLog::create(path).map_err(|source| open_error(path, source))
+        })
+        .transpose()?;
+    let recorder = stats::Recorder::new(stats, events, session_log).map(std::sync::Arc::new);
+    let (channels, mut manager) = spawn_process(&config, recorder).await?;
+    let success = if cli.no_tui {
+        headless::run_headless(channels, cli.fail_fast).await
+    } else {
+        run_tui(&config, &cli.config, channels, &mut manager).await?;
+        true
+    };
+    let code = match manager.exit_code() {
+        0 if !success => process::EXIT_FAILURE,
+        code => code,
+    };
     manager.stop_all();
-    Ok(())
+    if config.summary {
+        print!(
+            "{}",
+            summary::format_summary(&manager.statuses(), session_start.elapsed())
+        );
+    }
+    Ok(code)
+}
+
+/// Error for a file given on the command line that couldn't be opened.
+fn open_error(path: &str, source: std::io::Error) -> RunnerError {
+    RunnerError::Open {
+        path: path.to_string(),
+        source,
+    }
 }
@@ -0,0 +1,156 @@
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+    sync::Mutex,
+};
+
+/// Records the process groups of the running processes in a file, so that a
+/// later run can find the ones left behind if this one crashes.
+///
+/// Each line holds a process group ID and the process name. The file is
+/// rewritten whenever a process starts or stops, and removed on a clean exit.
+/// Orphans of a previous run that are still alive stay listed in it.
+pub struct StateFile {
+    path: PathBuf,
+    /// Name and process group of each process, in the order they were added.
+    entries: Mutex<Vec<(String, Option<i32>)>>,
+    leftovers: Vec<Orphan>,
+}
+
+impl StateFile {
+    pub fn new(path: &str, names: Vec<String>) -> Self {
+        StateFile {
+            path: PathBuf::from(path),
+            entries: Mutex::new(names.into_iter().map(|name| (name, None)).collect()),
+            leftovers: find_orphans(path),
+        }
+    }
+
+    /// Adds a process started after the others, returning its index.
+    pub fn add(&self, name: &str) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        entries.push((name.to_string(), None));
+        entries.len() - 1
+    }
+
+    /// Updates the process group of the process at `index` and rewrites the file.
+    pub fn record(&self, index: usize, pgid: Option<i32>) {
+        let mut entries = self.entries.lock().unwrap();
+        entries[index].1 = pgid;
+        self.write(&entries);
+    }
+
+    /// Removes the file, once every process has been stopped, unless it still
+    /// lists orphans of a previous run.
+    pub fn remove(&self) {
+        if self.leftovers.is_empty() {
+            let _ = fs::remove_file(&self.path);
+        } else {
+            let mut entries = self.entries.lock().unwrap();
+            entries.iter_mut().for_each(|(_, pgid)| *pgid = None);
+            self.write(&entries);
+        }
+    }
+
+    fn write(&self, entries: &[(String, Option<i32>)]) {
+        let running = entries
+            .iter()
+            .filter_map(|(name, pgid)| pgid.map(|pgid| (pgid, name.as_str())));
+        let leftovers = self.leftovers.iter().map(|o| (o.pgid, o.name.as_str()));
+        let contents: String = leftovers
+            .chain(running)
+            .map(|(pgid, name)| format!("{}\t{}\n", pgid, name))
+            .collect();
+        let _ = fs::write(&self.path, contents);
+    }
+}
+
+/// A process group recorded by a previous run that is still alive.
+pub struct Orphan {
+    pub pgid: i32,
+    pub name: String,
+}
+
+/// Reads the state file left by a previous run and returns the process groups
+/// in it that still exist. A missing file means the previous run exited cleanly.
+///
+/// Only process group IDs are compared, so a group ID reused by an unrelated
+/// program since the crash is reported too.
+#[cfg(unix)]
+pub fn find_orphans(path: &str) -> Vec<Orphan> {
+    use nix::sys::signal::killpg;
+    use nix::unistd::Pid;
+    let Ok(contents) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (pgid, name) = line.split_once('\t')?;
+            let pgid: i32 = pgid.parse().ok().filter(|&pgid| pgid > 1)?;
+            // Signal 0 only checks that the group exists.
+            killpg(Pid::from_raw(pgid), None).ok()?;
+            Some(Orphan {
+                pgid,
+                name: name.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(not(unix))]
+pub fn find_orphans(_path: &str) -> Vec<Orphan> {
+    Vec::new()
+}
+
+/// Kills the process groups of the given orphans, waiting up to a second for
+/// them to disappear.
+#[cfg(unix)]
+pub fn kill_orphans(orphans: &[Orphan]) {
+    use nix::sys::signal::{Signal, killpg};
+    use nix::unistd::Pid;
+    for orphan in orphans {
+        let _ = killpg(Pid::from_raw(orphan.pgid), Signal::SIGKILL);
+    }
+    for _ in 0..20 {
+        if orphans
+            .iter()
+            .all(|o| killpg(Pid::from_raw(o.pgid), None).is_err())
+        {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+}
+
+#[cfg(not(unix))]
+pub fn kill_orphans(_orphans: &[Orphan]) {}
+
+/// Looks for process groups left over by a crashed run and kills them, either
+/// right away when `force` is set or after confirmation on the terminal.
+///
+/// # Arguments
+/// * `path` - The state file configured with `state_file`.
+/// * `force` - Kill without asking, as with `--kill-orphans`.
+pub fn cleanup_orphans(path: &str, force: bool) -> io::Result<()> {
+    let orphans = find_orphans(path);
+    if orphans.is_empty() {
+        return Ok(());
+    }
+    println!("Found process groups left over by a previous run:");
+    for orphan in &orphans {
+        println!("  {}\t{}", orphan.pgid, orphan.name);
+    }
+    let kill = force || {
+        print!("Kill them before starting? [y/N] ");
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().lock().read_line(&mut answer)?;
+        matches!(answer.trim(), "y" | "Y" | "yes")
+    };
+    if kill {
+        kill_orphans(&orphans);
+    }
+    Ok(())
+}
@@ -1,21 +1,547 @@
-use crate::config::Config;
-use std::process::Stdio;
+use crate::ansi::{Hyperlink, StyledRange, extract_hyperlinks, extract_styles, show_controls};
+use crate::cgroup::Cgroup;
+use crate::config::{
+    Config, DEFAULT_COMMAND_CAPACITY, DEFAULT_OUTPUT_CAPACITY, Pattern, ProcessConfig,
+    RestartPolicy, expand_home, expand_vars, find_in_path, read_env_file, resolve_cwd,
+};
+use crate::error::RunnerError;
+use crate::orphans::StateFile;
+use crate::stats::{Event, Recorder};
+use crate::usage::{Sample, Usage, sample_group};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    ffi::{CString, OsStr, OsString},
+    fmt,
+    ops::Range,
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
 use tokio::{
     io::AsyncBufReadExt,
     process::Command,
-    sync::mpsc::{self, Receiver, Sender},
+    sync::{
+        Notify, OwnedSemaphorePermit, Semaphore,
+        mpsc::{self, Receiver, Sender},
+        oneshot,
+    },
+    task::JoinHandle,
 };
 
+/// How long a freshly spawned process counts as starting, for `max_concurrent_starts`.
+const STARTUP_WINDOW: Duration = Duration::from_secs(1);
+
+/// Delay before a failed attempt is retried, for `max_attempts`.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// How often a process waiting on `depends_on` checks its dependencies again.
+const DEPENDENCY_POLL: Duration = Duration::from_millis(100);
+
+/// Time a process is given to exit after SIGTERM when restarted, before it is killed.
+const RESTART_GRACE: Duration = Duration::from_secs(3);
+
+/// First delay before a process is started again by its `restart` policy,
+/// doubled after each restart in a row up to `RESTART_BACKOFF_MAX`.
+const RESTART_BACKOFF: Duration = Duration::from_millis(500);
+
+const RESTART_BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+/// A run lasting this long counts as healthy and resets the backoff of `restart`.
+const RESTART_BACKOFF_RESET: Duration = Duration::from_secs(60);
+
+/// How often the process left by a daemonizing command is checked, for `pidfile`.
+const DAEMON_POLL: Duration = Duration::from_millis(250);
+
+/// How long the exit of a process waits for the readers to take the rest of its
+/// output, so that the exit is recorded after the last lines. Processes it left
+/// running may keep the pipes open.
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// How often a process reported as silent, for `idle_timeout_ms`, is checked
+/// for output again.
+const IDLE_POLL: Duration = Duration::from_millis(250);
+
+/// How long a daemonizing command has to write its `pidfile` after exiting.
+const PIDFILE_TIMEOUT: Duration = Duration::from_secs(5);
+
 pub enum ProcessCommand {
     Start,
     Stop,
+    /// Stops the process if running and starts it again. With `cascade`, the
+    /// processes that list it in `restart_with` are then restarted too.
+    Restart {
+        cascade: bool,
+    },
+    /// Writes text to the standard input of the process, if it was started with `stdin`.
+    Input(String),
+    /// Logs a note added by the user to the pane, like a line of output.
+    Note(String),
+    /// Runs the process with these settings from now on, restarting it if it
+    /// is running, after a configuration reload changed its command line.
+    Replace(Box<ProcessConfig>),
+    /// Changes the command line of the process, restarting it if it is running.
+    /// The outcome, or why the update was rejected, is sent back on `reply`.
+    Reload {
+        update: CommandUpdate,
+        reply: oneshot::Sender<Result<(), String>>,
+    },
+}
+
+/// New command line settings for a process; `None` keeps the current value.
+#[derive(Debug, Default)]
+pub struct CommandUpdate {
+    pub command: Option<String>,
+    pub args: Option<Vec<String>>,
+    pub cwd: Option<String>,
+}
+
+/// Output stream a line was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// A line of process output, tagged with its source stream.
+#[derive(Debug, Clone)]
+pub struct OutputLine {
+    pub stream: Stream,
+    /// The visible text, without hyperlink escape sequences.
+    pub text: String,
+    /// Hyperlinks over parts of the text.
+    pub links: Vec<Hyperlink>,
+    /// Colors and attributes of parts of the text, from ANSI escape sequences.
+    pub styles: Vec<StyledRange>,
+    /// Byte ranges of the text that changed since the previous line matching
+    /// `diff_pattern`; filled in by the TUI.
+    pub changes: Vec<Range<usize>>,
+    /// A note added by the user to the timeline, not output of the process.
+    pub note: bool,
+    /// When the line was read, formatted with `timestamp_format`, if `timestamps` is set.
+    pub timestamp: Option<String>,
+    /// The line overwrites the previous one of its stream, which ended with a
+    /// carriage return instead of a line feed.
+    pub replaces: bool,
+}
+
+impl OutputLine {
+    /// A line of plain text on `stream`, without links, styles or timestamp.
+    pub fn new(stream: Stream, text: impl Into<String>) -> Self {
+        OutputLine {
+            stream,
+            text: text.into(),
+            links: Vec::new(),
+            styles: Vec::new(),
+            changes: Vec::new(),
+            note: false,
+            timestamp: None,
+            replaces: false,
+        }
+    }
+
+    /// A message of the runner about the process, on stderr and tagged `[runner]`.
+    pub fn runner(text: impl std::fmt::Display) -> Self {
+        OutputLine::new(Stream::Stderr, format!("[runner] {}", text))
+    }
+}
+
+/// The TUI side of a supervised process: its output, controls and status.
+pub struct ProcessHandle {
+    pub name: String,
+    pub output: Receiver<OutputLine>,
+    pub control: Sender<ProcessCommand>,
+    pub status: SharedStatus,
+}
+
+pub type OutputChannels = Vec<ProcessHandle>;
+
+/// Number of lines kept per process for `ProcessManager::recent_output` when
+/// `recent_output` is not set in the configuration.
+pub const DEFAULT_RECENT_OUTPUT: usize = 1000;
+
+/// The last lines of output of a process, kept apart from the TUI buffers so
+/// they can be queried at any time.
+///
+/// The output readers push to it while `ProcessManager::recent_output` reads
+/// from it; the lines are behind a mutex, so both are safe from any thread and
+/// a reader always sees whole lines in order.
+pub struct OutputRing {
+    capacity: usize,
+    lines: Mutex<VecDeque<OutputLine>>,
+}
+
+impl OutputRing {
+    /// Creates a ring keeping up to `capacity` lines; with 0 nothing is kept.
+    pub fn new(capacity: usize) -> Self {
+        OutputRing {
+            capacity,
+            lines: Mutex::new(VecDeque::with_capacity(capacity.min(DEFAULT_RECENT_OUTPUT))),
+        }
+    }
+
+    /// Appends a line, dropping the oldest one when the ring is full.
+    pub fn push(&self, line: OutputLine) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    /// Returns copies of the last `n` lines, oldest first.
+    pub fn last(&self, n: usize) -> Vec<OutputLine> {
+        let lines = self.lines.lock().unwrap();
+        lines
+            .range(lines.len().saturating_sub(n)..)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Lines read from a pipe and not yet delivered to the channel of the process.
+///
+/// The reader pushes without ever waiting, while the delivery task pops as
+/// the channel makes room. With a limit, the oldest lines are dropped once it
+/// is reached and the number dropped is handed out with the next line.
+struct LineQueue {
+    limit: Option<usize>,
+    state: Mutex<QueueState>,
+    added: Notify,
+}
+
+#[derive(Default)]
+struct QueueState {
+    lines: VecDeque<OutputLine>,
+    dropped: usize,
+    closed: bool,
+}
+
+impl LineQueue {
+    fn new(limit: Option<usize>) -> Self {
+        LineQueue {
+            limit,
+            state: Mutex::new(QueueState::default()),
+            added: Notify::new(),
+        }
+    }
+
+    /// Adds a line, unless the queue is closed.
+    fn push(&self, line: OutputLine) {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return;
+        }
+        if self.limit.is_some_and(|limit| state.lines.len() >= limit) {
+            state.lines.pop_front();
+            state.dropped += 1;
+        }
+        state.lines.push_back(line);
+        drop(state);
+        self.added.notify_one();
+    }
+
+    /// Marks the end of the stream, or of the delivery; the lines still queued
+    /// are delivered, if anything receives them.
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.added.notify_one();
+    }
+
+    /// Waits for the next line, with the number of lines dropped before it,
+    /// or returns `None` once the queue is closed and empty.
+    async fn pop(&self) -> Option<(OutputLine, usize)> {
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+                if let Some(line) = state.lines.pop_front() {
+                    return Some((line, std::mem::take(&mut state.dropped)));
+                }
+                if state.closed {
+                    return None;
+                }
+            }
+            self.added.notified().await;
+        }
+    }
 }
 
-pub type OutputChannels = Vec<(String, Receiver<String>, Sender<ProcessCommand>)>;
 pub type ProcessSpawnResult = (OutputChannels, ProcessManager);
 
+/// Lifecycle state of a supervised process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProcessState {
+    /// Not running, either never started or stopped by the user.
+    #[default]
+    Stopped,
+    Running,
+    /// The process ended on its own.
+    Exited,
+    /// The process could not be started.
+    Failed,
+}
+
+impl fmt::Display for ProcessState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ProcessState::Stopped => "stopped",
+            ProcessState::Running => "running",
+            ProcessState::Exited => "exited",
+            ProcessState::Failed => "failed",
+        };
+        f.write_str(s)
+    }
+}
+
+/// How a process ended: with an exit code or killed by a signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    Code(i32),
+    Signal(i32),
+}
+
+impl From<std::process::ExitStatus> for ExitReason {
+    fn from(status: std::process::ExitStatus) -> Self {
+        if let Some(code) = status.code() {
+            return ExitReason::Code(code);
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            if let Some(sig) = status.signal() {
+                return ExitReason::Signal(sig);
+            }
+        }
+        ExitReason::Code(-1)
+    }
+}
+
+impl fmt::Display for ExitReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExitReason::Code(code) => write!(f, "{}", code),
+            #[cfg(unix)]
+            ExitReason::Signal(sig) => match nix::sys::signal::Signal::try_from(*sig) {
+                Ok(signal) => f.write_str(signal.as_str()),
+                Err(_) => write!(f, "signal {}", sig),
+            },
+            #[cfg(not(unix))]
+            ExitReason::Signal(sig) => write!(f, "signal {}", sig),
+        }
+    }
+}
+
+/// Runtime accounting for a process, updated by its reader task.
+#[derive(Debug, Clone, Default)]
+pub struct ProcessStatus {
+    pub state: ProcessState,
+    /// How the last run ended, if it ended on its own.
+    pub exit: Option<ExitReason>,
+    /// Number of times the process has been launched.
+    pub starts: u32,
+    /// Total number of output lines received, across all runs.
+    pub lines: u64,
+    /// When the current run started, if the process is running.
+    pub started_at: Option<Instant>,
+    /// Accumulated running time of the previous runs.
+    pub runtime: Duration,
+    /// Time the latest start took to become ready: until a line matched
+    /// `ready_when`, or until the first output line without it.
+    pub startup_latency: Option<Duration>,
+    /// Why the latest start failed, if it did.
+    pub spawn_error: Option<String>,
+    /// Whether the latest start missed its `ready_timeout_ms`.
+    pub ready_timed_out: bool,
+    /// Dependencies not ready yet, while a start is held back by `depends_on`.
+    pub waiting_for: Vec<String>,
+    /// A start waits for a free slot, for `max_concurrent_starts`.
+    pub queued: bool,
+    /// When the process is started, while its start waits for `start_delay_ms`.
+    pub delayed_until: Option<Instant>,
+    /// Failed attempts in a row since the last start asked for by the user.
+    pub attempts: u32,
+    /// Whether another attempt is scheduled after a failure.
+    pub retrying: bool,
+    /// PID read from the `pidfile`, while the process it names is followed.
+    pub daemon_pid: Option<i32>,
+    /// CPU and memory used lately by the process group, with `show_usage`.
+    pub usage: Option<Usage>,
+    /// Runs that ended on their own other than with code 0, or couldn't be
+    /// started, over the whole session.
+    pub failed_runs: u32,
+    /// Start from which `recent_restarts` counts; moved up by a start asked for
+    /// by the user with `reset_restarts`.
+    pub counted_from: u32,
+    /// When the current run last wrote anything.
+    pub last_output: Option<Instant>,
+    /// No output came within `idle_timeout_ms`, until the next output.
+    pub idle: bool,
+    /// The process has `enabled: false` and wasn't started yet.
+    pub disabled: bool,
+    /// The process has `auto_start: false` and wasn't started yet.
+    pub on_demand: bool,
+    /// The process has a `ready_when` or a `ready_port`; without one, it is
+    /// ready as soon as it runs.
+    pub checks_ready: bool,
+    /// Command line the process runs, kept up to date across reloads.
+    pub command: String,
+}
+
+impl ProcessStatus {
+    pub fn restarts(&self) -> u32 {
+        self.starts.saturating_sub(1)
+    }
+
+    /// Restarts shown in the pane title: every start after the first one, or
+    /// with `reset_restarts`, after the latest start asked for by the user.
+    pub fn recent_restarts(&self) -> u32 {
+        self.starts.saturating_sub(self.counted_from.max(1))
+    }
+
+    /// Total running time, including the current run.
+    pub fn total_runtime(&self) -> Duration {
+        self.runtime + self.started_at.map(|t| t.elapsed()).unwrap_or_default()
+    }
+
+    fn mark_started(&mut self) {
+        self.state = ProcessState::Running;
+        self.exit = None;
+        self.starts += 1;
+        self.disabled = false;
+        self.on_demand = false;
+        self.started_at = Some(Instant::now());
+        self.startup_latency = None;
+        self.spawn_error = None;
+        self.ready_timed_out = false;
+        self.waiting_for.clear();
+        self.delayed_until = None;
+        self.queued = false;
+        self.retrying = false;
+        self.daemon_pid = None;
+        self.usage = None;
+        self.last_output = None;
+        self.idle = false;
+    }
+
+    /// Whether the process is done for now: it ended or was stopped and won't
+    /// be restarted, it waits for dependencies or it waits to be started by
+    /// the user. Right after launch, before its start is handled, it isn't.
+    pub fn is_settled(&self) -> bool {
+        match self.state {
+            ProcessState::Running => false,
+            ProcessState::Exited | ProcessState::Failed => !self.retrying,
+            ProcessState::Stopped => {
+                self.waits_for_user() || self.starts > 0 || !self.waiting_for.is_empty()
+            }
+        }
+    }
+
+    /// Whether the process runs or is on its way to: a start waits for its
+    /// dependencies, its `start_delay_ms` or a slot, or a retry is scheduled.
+    /// Stopping it is what takes it back to rest.
+    pub fn is_active(&self) -> bool {
+        self.state == ProcessState::Running
+            || self.retrying
+            || self.queued
+            || self.delayed_until.is_some()
+            || !self.waiting_for.is_empty()
+    }
+
+    /// Whether the process is left for the user to start, being disabled or
+    /// not started automatically, and wasn't started yet.
+    pub fn waits_for_user(&self) -> bool {
+        self.disabled || self.on_demand
+    }
+
+    /// Whether the process is running and either has announced that it is
+    /// ready or has no readiness check.
+    pub fn is_ready(&self) -> bool {
+        self.state == ProcessState::Running
+            && (self.startup_latency.is_some() || !self.checks_ready)
+    }
+
+    fn mark_failed(&mut self, error: String) {
+        self.state = ProcessState::Failed;
+        self.exit = None;
+        self.spawn_error = Some(error);
+        self.failed_runs += 1;
+    }
+
+    /// Records that the current run became ready at `now`, unless it already did.
+    /// Returns the startup latency if it was just recorded.
+    fn mark_ready(&mut self, now: Instant) -> Option<Duration> {
+        if self.startup_latency.is_none()
+            && let Some(started) = self.started_at
+        {
+            self.startup_latency = Some(now.saturating_duration_since(started));
+            self.ready_timed_out = false;
+            return self.startup_latency;
+        }
+        None
+    }
+
+    fn mark_finished(&mut self, state: ProcessState, exit: Option<ExitReason>) {
+        self.state = state;
+        self.exit = exit;
+        if exit.is_some_and(|exit| exit != ExitReason::Code(0)) {
+            self.failed_runs += 1;
+        }
+        self.daemon_pid = None;
+        self.usage = None;
+        if let Some(started) = self.started_at.take() {
+            self.runtime += started.elapsed();
+        }
+    }
+}
+
+pub type SharedStatus = Arc<Mutex<ProcessStatus>>;
+
+type SharedConfig = Arc<Mutex<ProcessConfig>>;
+
+/// Status of a process before its first start.
+fn initial_status(proc: &ProcessConfig) -> SharedStatus {
+    Arc::new(Mutex::new(ProcessStatus {
+        disabled: !proc.is_enabled(),
+        on_demand: proc.auto_start == Some(false),
+        checks_ready: proc.checks_ready(),
+        command: proc.command_line(),
+        ..Default::default()
+    }))
+}
+
+/// Exit status of the runner when a process failed during the session.
+pub const EXIT_FAILURE: i32 = 1;
+
+/// Exit status of the runner when a process was left waiting for a process in
+/// its `depends_on` that ended or couldn't be started.
+pub const EXIT_DEPENDENCY_FAILURE: i32 = 3;
+
 pub struct ProcessManager {
     control_senders: Vec<Sender<ProcessCommand>>,
+    names: Vec<String>,
+    statuses: Vec<SharedStatus>,
+    recent: Vec<Arc<OutputRing>>,
+    /// Settings each process was last started with, to compare on a reload.
+    /// Shared with its supervisor, which updates them when the control socket
+    /// changes its command line.
+    configs: Vec<SharedConfig>,
+    control_socket: Option<PathBuf>,
+    cgroup: Option<Cgroup>,
+    context: SupervisorContext,
+}
+
+/// What applying a new configuration did to the running processes.
+#[derive(Default)]
+pub struct ConfigChanges {
+    /// Handles of the processes added, which haven't been started yet.
+    pub added: Vec<ProcessHandle>,
+    /// Names of the processes removed, which are being stopped.
+    pub removed: Vec<String>,
+    /// Names of the processes restarted with a new command line or environment.
+    pub changed: Vec<String>,
 }
 
 impl Drop for ProcessManager {
@@ -27,6 +553,7 @@ impl Drop for ProcessManager {
         // Optionally: sleep a bit to allow processes to terminate
         // (tokio::time::sleep is async, so for Drop we can't await)
         std::thread::sleep(std::time::Duration::from_millis(200));
+        self.cleanup();
     }
 }
 
@@ -36,10 +563,161 @@ impl ProcessManager {
             let _ = tx.try_send(ProcessCommand::Stop);
         }
         std::thread::sleep(std::time::Duration::from_millis(200));
+        self.cleanup();
+    }
+
+    /// Removes the files kept while running: the state file and the control socket,
+    /// and the cgroup if it was created for this run.
+    fn cleanup(&self) {
+        if let Some(state_file) = &self.context.state_file {
+            state_file.remove();
+        }
+        if let Some(cgroup) = &self.cgroup {
+            cgroup.remove();
+        }
+        if let Some(path) = &self.control_socket {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    /// Applies a new configuration to the running processes, matched by name:
+    /// the processes added get a supervisor, the ones removed are stopped, and
+    /// the ones whose command line or environment changed are restarted with
+    /// it, if running. The others, and all other settings of the processes
+    /// kept, are left as they are. The control socket keeps knowing only the
+    /// processes there were at launch.
+    pub fn apply_config(&mut self, config: &Config) -> ConfigChanges {
+        let mut changes = ConfigChanges::default();
+        let mut i = 0;
+        while i < self.names.len() {
+            if config.processes.iter().any(|p| p.name == self.names[i]) {
+                i += 1;
+                continue;
+            }
+            // The task ends once the TUI drops its sender too.
+            let _ = self
+                .control_senders
+                .remove(i)
+                .try_send(ProcessCommand::Stop);
+            self.statuses.remove(i);
+            self.recent.remove(i);
+            self.configs.remove(i);
+            changes.removed.push(self.names.remove(i));
+        }
+        let mut added = Vec::new();
+        for (j, proc) in config.processes.iter().enumerate() {
+            match self.names.iter().position(|n| *n == proc.name) {
+                Some(i) if !self.configs[i].lock().unwrap().same_launch(proc) => {
+                    let mut launch = self.configs[i].lock().unwrap();
+                    launch.take_launch(proc);
+                    let replace = ProcessCommand::Replace(Box::new(launch.clone()));
+                    drop(launch);
+                    let _ = self.control_senders[i].try_send(replace);
+                    changes.changed.push(proc.name.clone());
+                }
+                Some(_) => {}
+                None => {
+                    let (cmd_tx, cmd_rx) =
+                        mpsc::channel::<ProcessCommand>(config.command_capacity());
+                    self.control_senders.push(cmd_tx);
+                    self.names.push(proc.name.clone());
+                    self.statuses.push(initial_status(proc));
+                    self.configs.push(Arc::new(Mutex::new(proc.clone())));
+                    added.push((j, cmd_rx));
+                }
+            }
+        }
+        // Started once all are known, as they may depend on each other.
+        for (j, cmd_rx) in added {
+            let proc = &config.processes[j];
+            let position = |name: &str| self.names.iter().position(|n| n == name);
+            let Some(i) = position(&proc.name) else {
+                continue;
+            };
+            let dependencies = config
+                .dependencies(j)
+                .into_iter()
+                .filter_map(|k| position(&config.processes[k].name))
+                .map(|k| (self.names[k].clone(), self.statuses[k].clone()))
+                .collect();
+            let cascade = config
+                .restart_cascade(j)
+                .into_iter()
+                .filter_map(|k| position(&config.processes[k].name))
+                .map(|k| self.control_senders[k].clone())
+                .collect();
+            let index = match &self.context.state_file {
+                Some(file) => file.add(&proc.name),
+                None => i,
+            };
+            let status = self.statuses[i].clone();
+            let (supervisor, rx, ring) = self.context.supervisor(
+                config,
+                proc,
+                index,
+                status.clone(),
+                self.configs[i].clone(),
+                dependencies,
+            );
+            spawn_reader(supervisor, cmd_rx, cascade);
+            self.recent.push(ring);
+            changes.added.push(ProcessHandle {
+                name: proc.name.clone(),
+                output: rx,
+                control: self.control_senders[i].clone(),
+                status,
+            });
+        }
+        changes
+    }
+
+    /// Returns a snapshot of every process status, paired with the process name.
+    pub fn statuses(&self) -> Vec<(String, ProcessStatus)> {
+        self.names
+            .iter()
+            .zip(&self.statuses)
+            .map(|(name, status)| (name.clone(), status.lock().unwrap().clone()))
+            .collect()
+    }
+
+    /// Returns the exit status for the runner, from what happened in the whole
+    /// session: `EXIT_DEPENDENCY_FAILURE` if a process is waiting for a
+    /// dependency that ended or couldn't be started, else `EXIT_FAILURE` if any
+    /// run ended with a nonzero code or a signal or couldn't be started, and 0
+    /// otherwise. Called before stopping the processes, which ends the waits.
+    pub fn exit_code(&self) -> i32 {
+        let statuses = self.statuses();
+        let ended = |name: &String| {
+            statuses.iter().any(|(n, status)| {
+                n == name
+                    && matches!(status.state, ProcessState::Exited | ProcessState::Failed)
+                    && !status.retrying
+            })
+        };
+        if statuses
+            .iter()
+            .any(|(_, status)| status.waiting_for.iter().any(ended))
+        {
+            EXIT_DEPENDENCY_FAILURE
+        } else if statuses.iter().any(|(_, status)| status.failed_runs > 0) {
+            EXIT_FAILURE
+        } else {
+            0
+        }
+    }
+
+    /// Returns the last `n` lines of output of the named process, oldest first,
+    /// or `None` if there is no process with that name. At most `recent_output`
+    /// lines are kept per process, independently of the TUI buffers. It can be
+    /// called at any time while the processes keep writing.
+    pub fn recent_output(&self, name: &str, n: usize) -> Option<Vec<OutputLine>> {
+        let index = self.names.iter().position(|n| n == name)?;
+        Some(self.recent[index].last(n))
     }
 }
 
 /// Spawns all processes defined in the config and returns their output channels and control senders.
+/// Lifecycle events and output lines are recorded in `recorder`, if given.
 ///
 /// For each process in the configuration, this function:
 /// - Creates a channel for receiving output lines from the process.
@@ -47,160 +725,1649 @@ impl ProcessManager {
 /// - Spawns a task to manage the process lifecycle and output forwarding.
 /// - Collects the process name, output receiver, and control sender into a vector.
 ///
-/// Returns a vector of tuples, each containing:
-/// - The process name (String)
-/// - The receiver for output lines (Receiver<String>)
-/// - The sender for control commands (Sender<ProcessCommand>)
+/// Returns a `ProcessHandle` per process, in configuration order, with its
+/// name, the receiver of its output lines, the sender of its commands and its
+/// status, along with the `ProcessManager` of them all. Nothing runs until a
+/// `ProcessCommand::Start` is sent.
 pub async fn spawn_process(
     config: &Config,
-) -> Result<ProcessSpawnResult, Box<dyn std::error::Error>> {
+    recorder: Option<Arc<Recorder>>,
+) -> Result<ProcessSpawnResult, RunnerError> {
     let mut channels = Vec::new();
-    let mut control_senders = Vec::new();
-    for proc in &config.processes {
-        let (tx, rx) = mpsc::channel::<String>(100);
-        let (cmd_tx, cmd_rx) = mpsc::channel::<ProcessCommand>(10);
-        spawn_reader(
-            proc.command.clone(),
-            proc.args.clone(),
-            proc.cwd.clone(),
-            tx,
-            cmd_rx,
+    let mut recent = Vec::new();
+    let (control_senders, control_receivers): (Vec<_>, Vec<_>) = config
+        .processes
+        .iter()
+        .map(|_| mpsc::channel::<ProcessCommand>(config.command_capacity()))
+        .unzip();
+    let state_file = config.state_file.as_deref().map(|path| {
+        let names = config.processes.iter().map(|p| p.name.clone()).collect();
+        Arc::new(StateFile::new(path, names))
+    });
+    let mut warning = None;
+    let cgroup = config
+        .cgroup
+        .as_ref()
+        .and_then(|settings| match Cgroup::prepare(settings) {
+            Ok(cgroup) => Some(cgroup),
+            Err(e) => {
+                warning = Some(format!("not using cgroup '{}': {}", settings.path, e));
+                None
+            }
+        });
+    let context = SupervisorContext {
+        start_limit: config
+            .max_concurrent_starts
+            .map(|n| Arc::new(Semaphore::new(n))),
+        state_file,
+        setup: Arc::new(ChildSetup {
+            nice: config.nice,
+            cgroup_procs: cgroup
+                .as_ref()
+                .and_then(|c| CString::new(c.procs_file().to_string_lossy().into_owned()).ok()),
+        }),
+        recorder,
+        warning,
+    };
+    let names: Vec<String> = config.processes.iter().map(|p| p.name.clone()).collect();
+    let shared: Vec<SharedStatus> = config.processes.iter().map(initial_status).collect();
+    let configs: Vec<SharedConfig> = config
+        .processes
+        .iter()
+        .map(|proc| Arc::new(Mutex::new(proc.clone())))
+        .collect();
+    for (i, (proc, cmd_rx)) in config.processes.iter().zip(control_receivers).enumerate() {
+        let dependencies = config
+            .dependencies(i)
+            .into_iter()
+            .map(|j| (names[j].clone(), shared[j].clone()))
+            .collect();
+        let cascade = config
+            .restart_cascade(i)
+            .into_iter()
+            .map(|j| control_senders[j].clone())
+            .collect();
+        let (supervisor, rx, ring) = context.supervisor(
+            config,
+            proc,
+            i,
+            shared[i].clone(),
+            configs[i].clone(),
+            dependencies,
         );
-        control_senders.push(cmd_tx.clone());
-        channels.push((proc.name.clone(), rx, cmd_tx));
+        spawn_reader(supervisor, cmd_rx, cascade);
+        recent.push(ring);
+        channels.push(ProcessHandle {
+            name: proc.name.clone(),
+            output: rx,
+            control: control_senders[i].clone(),
+            status: shared[i].clone(),
+        });
     }
-    let manager = ProcessManager { control_senders };
+    if let Some(path) = &config.control_socket {
+        crate::control::spawn_control_socket(path, names.clone(), control_senders.clone())
+            .map_err(RunnerError::ControlSocket)?;
+    }
+    let manager = ProcessManager {
+        control_senders,
+        names,
+        statuses: shared,
+        recent,
+        configs,
+        control_socket: config.control_socket.as_ref().map(PathBuf::from),
+        cgroup,
+        context,
+    };
     Ok((channels, manager))
 }
 
+/// What the supervisors of all the processes share.
+struct SupervisorContext {
+    /// Limit on the number of processes starting at once, for `max_concurrent_starts`.
+    start_limit: Option<Arc<Semaphore>>,
+    state_file: Option<Arc<StateFile>>,
+    /// Niceness and cgroup shared by all the processes.
+    setup: Arc<ChildSetup>,
+    recorder: Option<Arc<Recorder>>,
+    /// Shown first in every pane, when the cgroup couldn't be set up.
+    warning: Option<String>,
+}
+
+impl SupervisorContext {
+    /// Creates the supervisor of a process, at `index` in the state file, along
+    /// with the receiver of its output lines and the ring of its recent output.
+    /// It keeps `launch` up to date with the command line it runs.
+    fn supervisor(
+        &self,
+        config: &Config,
+        proc: &ProcessConfig,
+        index: usize,
+        status: SharedStatus,
+        launch: SharedConfig,
+        dependencies: Vec<(String, SharedStatus)>,
+    ) -> (Supervisor, Receiver<OutputLine>, Arc<OutputRing>) {
+        let (tx, rx) = mpsc::channel::<OutputLine>(config.output_capacity());
+        if let Some(warning) = &self.warning {
+            let _ = tx.try_send(OutputLine::runner(warning));
+        }
+        let line_buffered = proc.line_buffered.unwrap_or(config.line_buffered);
+        let ring = Arc::new(OutputRing::new(
+            config.recent_output.unwrap_or(DEFAULT_RECENT_OUTPUT),
+        ));
+        let mut log = None;
+        if let Some(template) = &proc.log_file {
+            let path = template.replace("{name}", &proc.name);
+            match std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+            {
+                Ok(file) => log = Some(Mutex::new(file)),
+                Err(e) => {
+                    let _ = tx.try_send(OutputLine::runner(format!(
+                        "not writing log file '{}': {}",
+                        path, e
+                    )));
+                }
+            }
+        }
+        let rules = OutputRules {
+            ignore: proc.ignore.clone(),
+            ready_when: proc.ready_when.clone(),
+            ready_port: proc.ready_port.is_some(),
+            timestamp_format: config
+                .timestamps
+                .then(|| config.timestamp_format().to_string()),
+            log,
+            ansi: proc.ansi.unwrap_or(true),
+            queue_limit: config.max_queued_lines,
+        };
+        let supervisor = Supervisor {
+            rules: Arc::new(rules),
+            start_limit: self.start_limit.clone(),
+            state_file: self.state_file.clone().map(|file| (file, index)),
+            recent: ring.clone(),
+            setup: self.setup.clone(),
+            dependencies,
+            recorder: self.recorder.clone(),
+            usage_every: config.usage_interval(),
+            reset_restarts: proc.reset_restarts.unwrap_or(config.reset_restarts),
+            launch: Some(launch),
+            config_dir: config.dir.clone(),
+            ..Supervisor::new(proc.clone(), line_buffered, tx, status)
+        };
+        (supervisor, rx, ring)
+    }
+}
+
+/// A command run once outside the configuration, such as an ad-hoc command typed
+/// in the TUI. It is supervised like a configured process and killed when dropped.
+pub struct OneOff {
+    pub output: Receiver<OutputLine>,
+    control: Sender<ProcessCommand>,
+    status: SharedStatus,
+}
+
+impl OneOff {
+    /// Kills the command if it is still running.
+    pub fn cancel(&self) {
+        let _ = self.control.try_send(ProcessCommand::Stop);
+    }
+
+    pub fn status(&self) -> ProcessStatus {
+        self.status.lock().unwrap().clone()
+    }
+}
+
+/// Runs a command line once through the shell, capturing its stdout and stderr.
+pub fn spawn_oneoff(line: &str) -> OneOff {
+    let proc = ProcessConfig {
+        name: line.to_string(),
+        command: line.to_string(),
+        shell: true,
+        ..Default::default()
+    };
+    let (tx, output) = mpsc::channel::<OutputLine>(DEFAULT_OUTPUT_CAPACITY);
+    let (control, cmd_rx) = mpsc::channel::<ProcessCommand>(DEFAULT_COMMAND_CAPACITY);
+    let status = SharedStatus::default();
+    spawn_reader(
+        Supervisor::new(proc, false, tx, status.clone()),
+        cmd_rx,
+        Vec::new(),
+    );
+    let _ = control.try_send(ProcessCommand::Start);
+    OneOff {
+        output,
+        control,
+        status,
+    }
+}
+
+/// Runs a command line through the shell in the background, with no input and
+/// its output discarded, without waiting for it.
+pub fn spawn_detached(line: &str) -> std::io::Result<()> {
+    let (shell, args) = shell_command(line, &[]);
+    // The child is reaped in the background once dropped.
+    Command::new(shell)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    Ok(())
+}
+
 /// Spawns a process reader task that manages process lifecycle and output forwarding.
 ///
 /// This function launches an asynchronous task that:
-/// - Listens for start/stop commands via a channel.
+/// - Listens for start/stop/restart commands via a channel.
 /// - When started, spawns the child process and sets its process group.
-/// - Forwards the process's stdout and stderr lines to the provided channel.
+/// - Forwards the process's stdout and stderr lines to the output channel.
 /// - When stopped, kills the process and its process group.
+/// - After a restart, restarts the processes in `cascade`, in order.
+/// - Records starts, stops and exits in the shared status.
 /// - Cleans up resources when the task ends.
+///
+/// Manual actions win over automatic ones. `Start` and a propagating `Restart`
+/// come from the user: they reset the failed attempts, so a process that gave
+/// up after `max_attempts` starts afresh. `Stop` holds the process: pending
+/// retries and dependency waits are dropped, and no retry or cascaded restart
+/// starts it again until the next manual start.
 fn spawn_reader(
-    command: String,
-    args: Vec<String>,
-    cwd: String,
-    tx: Sender<String>,
+    mut supervisor: Supervisor,
     mut cmd_rx: Receiver<ProcessCommand>,
+    cascade: Vec<Sender<ProcessCommand>>,
 ) {
     tokio::spawn(async move {
-        let mut child = None;
-        let mut child_pgid = None;
-        while let Some(cmd) = cmd_rx.recv().await {
-            match cmd {
-                ProcessCommand::Start => {
-                    if child.is_none() {
-                        let (mut spawned, pgid) = unsafe { spawn_child(&command, &args, &cwd) };
-                        child_pgid = pgid;
-                        spawn_output_readers(&mut spawned, &tx);
-                        child = Some(spawned);
+        loop {
+            let idle_at = supervisor.idle_deadline();
+            tokio::select! {
+                cmd = cmd_rx.recv() => match cmd {
+                    Some(ProcessCommand::Start) => supervisor.manual_start().await,
+                    Some(ProcessCommand::Stop) => {
+                        supervisor.held = true;
+                        supervisor.stop().await
+                    }
+                    Some(ProcessCommand::Restart { cascade: propagate }) => {
+                        if propagate {
+                            supervisor.record(Event::Restart);
+                            supervisor.stop_gracefully().await;
+                            supervisor.manual_start().await;
+                        } else if supervisor.running() && !supervisor.held {
+                            // A cascaded restart leaves alone a dependent that isn't running.
+                            supervisor.record(Event::Restart);
+                            supervisor.stop_gracefully().await;
+                            supervisor.start().await;
+                        }
+                        if propagate {
+                            for dependent in &cascade {
+                                let _ = dependent.send(ProcessCommand::Restart { cascade: false }).await;
+                            }
+                        }
                     }
+                    Some(ProcessCommand::Input(text)) => supervisor.write_input(&text).await,
+                    Some(ProcessCommand::Note(text)) => supervisor.note(text),
+                    Some(ProcessCommand::Replace(config)) => supervisor.replace(*config).await,
+                    Some(ProcessCommand::Reload { update, reply }) => {
+                        let _ = reply.send(supervisor.reload(update).await);
+                    }
+                    None => break,
+                },
+                result = wait_child(&mut supervisor.child) => supervisor.exited(result).await,
+                _ = startup_elapsed(&supervisor.start_permit) => supervisor.start_permit = None,
+                permit = start_slot(supervisor.start_limit.clone().filter(|_| supervisor.queued)) => {
+                    supervisor.take_slot(permit);
+                    supervisor.launch().await
+                }
+                _ = dependency_poll(supervisor.waiting) => supervisor.start().await,
+                _ = daemon_poll(supervisor.daemon.is_some()) => supervisor.check_daemon().await,
+                _ = usage_poll(supervisor.usage_every.filter(|_| supervisor.child_pgid.is_some())) => {
+                    supervisor.sample_usage()
                 }
-                ProcessCommand::Stop => {
-                    stop_child(&mut child, &mut child_pgid);
+                _ = retry_due(supervisor.start_at) => {
+                    supervisor.start_at = None;
+                    if !supervisor.running() {
+                        supervisor.launch().await
+                    }
+                }
+                _ = retry_due(idle_at) => supervisor.check_idle().await,
+                _ = retry_due(supervisor.retry_at) => {
+                    supervisor.retry_at = None;
+                    if !supervisor.held {
+                        supervisor.start().await
+                    }
                 }
             }
         }
-        stop_child(&mut child, &mut child_pgid);
+        supervisor.stop().await;
     });
 }
 
-/// Spawns a new process with the given command, arguments, and working directory.
+/// Lifecycle state owned by a reader task: what to run and the running child, if any.
+struct Supervisor {
+    config: ProcessConfig,
+    line_buffered: bool,
+    rules: Arc<OutputRules>,
+    tx: Sender<OutputLine>,
+    status: SharedStatus,
+    child: Option<tokio::process::Child>,
+    child_pgid: Option<i32>,
+    /// Shared limit on the number of processes starting at once.
+    start_limit: Option<Arc<Semaphore>>,
+    /// Slot held while the child is starting, with the time it is released.
+    start_permit: Option<(OwnedSemaphorePermit, tokio::time::Instant)>,
+    /// A launch waits for a free start slot.
+    queued: bool,
+    /// File recording the process group, with the index of this process in it.
+    state_file: Option<(Arc<StateFile>, usize)>,
+    /// Last lines of output, for `ProcessManager::recent_output`.
+    recent: Arc<OutputRing>,
+    /// Niceness and cgroup shared by all the processes.
+    setup: Arc<ChildSetup>,
+    /// Names and statuses of the processes listed in `depends_on`.
+    dependencies: Vec<(String, SharedStatus)>,
+    /// Whether a start is held back until the dependencies are ready.
+    waiting: bool,
+    /// When the next attempt is due after a failure, for `max_attempts`.
+    retry_at: Option<tokio::time::Instant>,
+    /// When a start delayed by `start_delay_ms` is due.
+    start_at: Option<tokio::time::Instant>,
+    /// How often to sample the resource usage, with `show_usage`.
+    usage_every: Option<Duration>,
+    /// The latest sample of the process group, to measure the CPU used since.
+    last_sample: Option<Sample>,
+    /// Stopped by the user: nothing starts it again but a manual start.
+    held: bool,
+    /// A start asked for by the user sets the restarts shown back to 0.
+    reset_restarts: bool,
+    /// Copy of the settings kept by the `ProcessManager`, for `reload`.
+    launch: Option<SharedConfig>,
+    /// Directory of the configuration file, that a relative `cwd` given to
+    /// `reload` is taken from.
+    config_dir: PathBuf,
+    /// Where lifecycle events and output lines are recorded, for `--stats-csv`
+    /// and `--events`.
+    recorder: Option<Arc<Recorder>>,
+    /// Process left running by a daemonizing command, once it exited.
+    daemon: Option<Daemon>,
+    /// Delay before the next restart by the `restart` policy.
+    backoff: Duration,
+    /// Standard input of the child, for `stdin`; kept apart as waiting for the child closes it.
+    stdin: Option<tokio::process::ChildStdin>,
+    /// Tasks reading the output of the child, until it ends.
+    readers: Vec<JoinHandle<()>>,
+}
+
+/// The process a daemonizing command leaves running, followed through its
+/// `pidfile` in place of the command once it exits.
+struct Daemon {
+    /// PID read from the pidfile; `None` until it is written.
+    pid: Option<i32>,
+    /// When the command was launched; an older pidfile is left over from a previous run.
+    launched: SystemTime,
+    /// When to give up waiting for the pidfile.
+    deadline: Instant,
+}
+
+impl Supervisor {
+    /// Creates the supervisor of a process, whose command is wrapped with `stdbuf`
+    /// if `line_buffered` is set. Starts are unlimited and nothing is recorded
+    /// or kept.
+    fn new(
+        proc: ProcessConfig,
+        line_buffered: bool,
+        tx: Sender<OutputLine>,
+        status: SharedStatus,
+    ) -> Self {
+        Supervisor {
+            rules: Arc::new(OutputRules {
+                ignore: proc.ignore.clone(),
+                ready_when: proc.ready_when.clone(),
+                ready_port: proc.ready_port.is_some(),
+                timestamp_format: None,
+                log: None,
+                ansi: proc.ansi.unwrap_or(true),
+                queue_limit: None,
+            }),
+            config: proc,
+            line_buffered,
+            tx,
+            status,
+            child: None,
+            child_pgid: None,
+            start_limit: None,
+            start_permit: None,
+            queued: false,
+            state_file: None,
+            recent: Arc::new(OutputRing::new(0)),
+            setup: Arc::new(ChildSetup::default()),
+            dependencies: Vec::new(),
+            waiting: false,
+            retry_at: None,
+            start_at: None,
+            usage_every: None,
+            last_sample: None,
+            held: false,
+            reset_restarts: false,
+            launch: None,
+            config_dir: PathBuf::new(),
+            recorder: None,
+            daemon: None,
+            backoff: RESTART_BACKOFF,
+            stdin: None,
+            readers: Vec::new(),
+        }
+    }
+
+    /// Whether the child, or the process it left behind as a daemon, is running.
+    fn running(&self) -> bool {
+        self.child.is_some() || self.daemon.is_some()
+    }
+
+    /// Records a lifecycle event, if there is anywhere to.
+    fn record(&self, event: Event) {
+        if let Some(recorder) = &self.recorder {
+            recorder.record(&self.config.name, &event);
+        }
+    }
+
+    /// Starts the child on behalf of the user, lifting a hold and forgetting the
+    /// failed attempts.
+    async fn manual_start(&mut self) {
+        self.held = false;
+        self.reset_attempts();
+        self.start().await;
+    }
+
+    /// Forgets the failed attempts and the restart backoff, before a start asked
+    /// for by the user. With `reset_restarts`, the restarts are counted again
+    /// from that start.
+    fn reset_attempts(&mut self) {
+        self.retry_at = None;
+        self.backoff = RESTART_BACKOFF;
+        let mut status = self.status.lock().unwrap();
+        status.attempts = 0;
+        status.retrying = false;
+        if self.reset_restarts {
+            status.counted_from = status.starts + 1;
+        }
+    }
+
+    /// Counts a failed attempt and schedules the next one while `max_attempts`
+    /// allows it, or, without it, when the `restart` policy restarts failures.
+    /// Returns whether another attempt was scheduled.
+    fn attempt_failed(&mut self) -> bool {
+        let retry = {
+            let mut status = self.status.lock().unwrap();
+            status.attempts += 1;
+            status.retrying = !self.held
+                && match self.config.max_attempts {
+                    Some(max) => status.attempts < max,
+                    None => self.config.restart != RestartPolicy::Never,
+                };
+            status.retrying
+        };
+        if retry {
+            let delay = self.retry_delay();
+            self.retry_at = Some(tokio::time::Instant::now() + delay);
+        }
+        retry
+    }
+
+    /// Returns the delay before the next attempt: fixed for `max_attempts`
+    /// alone, growing with each restart in a row with a `restart` policy.
+    fn retry_delay(&mut self) -> Duration {
+        if self.config.restart == RestartPolicy::Never {
+            return RETRY_DELAY;
+        }
+        let delay = self.backoff;
+        self.backoff = (self.backoff * 2).min(RESTART_BACKOFF_MAX);
+        delay
+    }
+
+    /// Returns the names of the dependencies that are not ready yet.
+    fn unready_dependencies(&self) -> Vec<String> {
+        self.dependencies
+            .iter()
+            .filter(|(_, status)| !status.lock().unwrap().is_ready())
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Updates the recorded process group of this process, if it is recorded.
+    fn record_pgid(&self) {
+        if let Some((file, index)) = &self.state_file {
+            file.record(*index, self.child_pgid);
+        }
+    }
+
+    /// Spawns the child unless it is already running. While a process in
+    /// `depends_on` is not ready, the start is held back and the reader task
+    /// tries again later. Once they are, a `start_delay_ms` postpones it further.
+    async fn start(&mut self) {
+        if !self.running() {
+            let unready = self.unready_dependencies();
+            self.waiting = !unready.is_empty();
+            if self.waiting {
+                self.status.lock().unwrap().waiting_for = unready;
+                return;
+            }
+            if let Some(ms) = self.config.start_delay_ms.filter(|&ms| ms > 0) {
+                if self.start_at.is_none() {
+                    let delay = Duration::from_millis(ms);
+                    self.start_at = Some(tokio::time::Instant::now() + delay);
+                    let mut status = self.status.lock().unwrap();
+                    status.waiting_for.clear();
+                    status.delayed_until = Some(Instant::now() + delay);
+                }
+                return;
+            }
+            self.launch().await;
+        }
+    }
+
+    /// Spawns the child once it holds a start slot, when starts are limited.
+    /// Without a free slot the launch is queued, and the reader task launches
+    /// again when one frees up. If the child can't be spawned, the error is
+    /// shown as an stderr line and recorded in the status.
+    async fn launch(&mut self) {
+        if let Some(limit) = &self.start_limit
+            && self.start_permit.is_none()
+        {
+            match limit.clone().try_acquire_owned() {
+                Ok(permit) => self.take_slot(permit),
+                Err(_) => {
+                    self.set_queued(true);
+                    return;
+                }
+            }
+        }
+        let spawned = prepare_child(&self.config).and_then(|(proc, env)| {
+            let (command, args) = if proc.shell {
+                shell_command(&proc.command, &proc.args)
+            } else {
+                (proc.command.clone(), proc.args.clone())
+            };
+            let (command, args) = command_line(&command, &args, self.line_buffered);
+            unsafe { spawn_child(&command, &args, &proc, &env, &self.setup) }
+        });
+        let (mut spawned, pgid) = match spawned {
+            Ok(spawned) => spawned,
+            Err(e) => {
+                let error = format!("failed to start '{}': {}", self.config.command, e);
+                self.start_permit = None;
+                let _ = self.tx.send(OutputLine::runner(&error)).await;
+                self.status.lock().unwrap().mark_failed(error);
+                self.record(Event::Failed);
+                self.attempt_failed();
+                return;
+            }
+        };
+        self.child_pgid = pgid;
+        self.last_sample = None;
+        let recorder = self
+            .recorder
+            .clone()
+            .map(|recorder| (recorder, self.config.name.clone()));
+        self.readers = spawn_output_readers(
+            &mut spawned,
+            &self.tx,
+            &self.status,
+            &self.rules,
+            &self.recent,
+            &recorder,
+        );
+        self.stdin = spawned.stdin.take();
+        self.child = Some(spawned);
+        self.status.lock().unwrap().mark_started();
+        spawn_readiness_probe(&self.config, &self.status, recorder);
+        self.record(Event::Start);
+        self.record_pgid();
+    }
+
+    /// Records whether a launch waits for a start slot, in the status too.
+    fn set_queued(&mut self, queued: bool) {
+        self.queued = queued;
+        self.status.lock().unwrap().queued = queued;
+    }
+
+    /// Holds a start slot for the `STARTUP_WINDOW` of the launch.
+    fn take_slot(&mut self, permit: OwnedSemaphorePermit) {
+        self.set_queued(false);
+        let deadline = tokio::time::Instant::now() + STARTUP_WINDOW;
+        self.start_permit = Some((permit, deadline));
+    }
+
+    /// When the child will have been silent for `idle_timeout_ms`, while it
+    /// runs. Once it was reported as silent, when to look again for the output
+    /// to resume, which starts a new wait.
+    fn idle_deadline(&self) -> Option<tokio::time::Instant> {
+        let timeout = self
+            .config
+            .idle_timeout_ms
+            .filter(|_| self.child.is_some())
+            .map(Duration::from_millis)?;
+        let status = self.status.lock().unwrap();
+        if status.idle {
+            return Some(tokio::time::Instant::now() + IDLE_POLL);
+        }
+        let since = status.last_output.or(status.started_at)?;
+        Some(tokio::time::Instant::from_std(since + timeout))
+    }
+
+    /// Reports the child as silent once it wrote nothing for `idle_timeout_ms`,
+    /// with a line in the output. Output that came meanwhile only moves the
+    /// deadline.
+    async fn check_idle(&mut self) {
+        if self.status.lock().unwrap().idle {
+            return;
+        }
+        let Some(deadline) = self.idle_deadline() else {
+            return;
+        };
+        if deadline > tokio::time::Instant::now() {
+            return;
+        }
+        self.status.lock().unwrap().idle = true;
+        let timeout = Duration::from_millis(self.config.idle_timeout_ms.unwrap_or_default());
+        let _ = self
+            .tx
+            .send(OutputLine::runner(format!(
+                "no output for {}",
+                crate::summary::format_duration(timeout)
+            )))
+            .await;
+    }
+
+    /// Samples the CPU and memory used by the process group, for `show_usage`.
+    /// The first sample of a run only sets the baseline for the next one.
+    fn sample_usage(&mut self) {
+        let Some(sample) = self.child_pgid.and_then(sample_group) else {
+            return;
+        };
+        if let Some(earlier) = self.last_sample.replace(sample) {
+            self.status.lock().unwrap().usage = Some(sample.usage_since(&earlier));
+        }
+    }
+
+    /// Kills the child and its process group, if running, or the daemon it left.
+    async fn stop(&mut self) {
+        self.start_permit = None;
+        self.set_queued(false);
+        self.stdin = None;
+        if self.retry_at.take().is_some() {
+            self.status.lock().unwrap().retrying = false;
+        }
+        if self.start_at.take().is_some() {
+            self.status.lock().unwrap().delayed_until = None;
+        }
+        if self.waiting {
+            self.waiting = false;
+            self.status.lock().unwrap().waiting_for.clear();
+        }
+        let daemon = self.daemon.take();
+        if let Some(pid) = daemon.as_ref().and_then(|d| d.pid) {
+            kill_daemon(pid, self.child_pgid.take());
+        }
+        if stop_child(&mut self.child, &mut self.child_pgid).await || daemon.is_some() {
+            self.status
+                .lock()
+                .unwrap()
+                .mark_finished(ProcessState::Stopped, None);
+            self.record(Event::Stop);
+            self.record_pgid();
+        }
+    }
+
+    /// Asks the child to exit with SIGTERM to its process group, then stops it
+    /// as usual, killing whatever is left after `RESTART_GRACE` (Unix only;
+    /// elsewhere the child is killed right away).
+    async fn stop_gracefully(&mut self) {
+        #[cfg(unix)]
+        if let (Some(child), Some(pgid)) = (&mut self.child, self.child_pgid) {
+            use nix::sys::signal::{Signal, killpg};
+            use nix::unistd::Pid;
+            let _ = killpg(Pid::from_raw(pgid), Signal::SIGTERM);
+            let _ = tokio::time::timeout(RESTART_GRACE, child.wait()).await;
+        }
+        self.stop().await;
+    }
+
+    /// Writes to the standard input of the child, if it has one. Once the child
+    /// closes it, the input is dropped.
+    async fn write_input(&mut self, text: &str) {
+        use tokio::io::AsyncWriteExt;
+        if let Some(stdin) = &mut self.stdin
+            && (stdin.write_all(text.as_bytes()).await.is_err() || stdin.flush().await.is_err())
+        {
+            self.stdin = None;
+        }
+    }
+
+    /// Keeps a note of the user along with the output, in the log file, the
+    /// event stream and the recent output. The pane shows it already.
+    fn note(&self, text: String) {
+        let line = OutputLine {
+            note: true,
+            ..OutputLine::new(Stream::Stdout, text)
+        };
+        let recorder = self
+            .recorder
+            .clone()
+            .map(|recorder| (recorder, self.config.name.clone()));
+        keep_line(&self.status, &self.rules, &recorder, &self.recent, &line);
+    }
+
+    /// Takes new settings, and shows their command line in the status.
+    fn set_config(&mut self, config: ProcessConfig) {
+        self.status.lock().unwrap().command = config.command_line();
+        self.config = config;
+    }
+
+    /// Runs the process with new settings, restarting it if it is running;
+    /// the restart is announced in the pane.
+    async fn replace(&mut self, config: ProcessConfig) {
+        self.set_config(config);
+        if self.running() {
+            let _ = self
+                .tx
+                .send(OutputLine::runner("configuration changed, restarting"))
+                .await;
+            self.record(Event::Restart);
+            self.stop_gracefully().await;
+            self.start().await;
+        }
+    }
+
+    /// Applies a new command line after checking that the working directory
+    /// exists and the command can be found, then restarts the child gracefully
+    /// if it is running. The `ProcessManager` learns the new command line too.
+    async fn reload(&mut self, update: CommandUpdate) -> Result<(), String> {
+        let mut config = self.config.clone();
+        if let Some(command) = update.command {
+            config.command = command;
+        }
+        if let Some(args) = update.args {
+            config.args = args;
+        }
+        if let Some(cwd) = update.cwd {
+            config.cwd = Some(resolve_cwd(&cwd, &self.config_dir));
+        }
+        config.check_launch()?;
+        if let Some(launch) = &self.launch {
+            launch.lock().unwrap().take_launch(&config);
+        }
+        self.set_config(config);
+        if self.running() {
+            self.record(Event::Restart);
+            self.stop_gracefully().await;
+            self.start().await;
+        }
+        Ok(())
+    }
+
+    /// Records that the child ended on its own. With a `pidfile`, an exit with
+    /// code 0 means the command daemonized itself: the process stays running
+    /// while the one named in the pidfile is followed instead.
+    async fn exited(&mut self, result: std::io::Result<std::process::ExitStatus>) {
+        self.start_permit = None;
+        self.child = None;
+        self.stdin = None;
+        release_job(self.child_pgid.take());
+        let readers = std::mem::take(&mut self.readers);
+        let drained = async {
+            for reader in readers {
+                let _ = reader.await;
+            }
+        };
+        let _ = tokio::time::timeout(OUTPUT_DRAIN_TIMEOUT, drained).await;
+        let exit = result.ok().map(ExitReason::from);
+        if self.config.pidfile.is_some() && exit == Some(ExitReason::Code(0)) {
+            let started = self.status.lock().unwrap().started_at;
+            self.daemon = Some(Daemon {
+                pid: None,
+                launched: SystemTime::now() - started.map(|t| t.elapsed()).unwrap_or_default(),
+                deadline: Instant::now() + PIDFILE_TIMEOUT,
+            });
+            self.record_pgid();
+            return;
+        }
+        self.finished(exit).await;
+    }
+
+    /// Records that the process ended. With `max_attempts` or a `restart`
+    /// policy, an exit other than with code 0 counts as a failed attempt: the
+    /// process is started again after a delay, or marked as failed once the
+    /// attempts are used up. With `restart: always`, an exit with code 0 starts
+    /// it again too, without counting as a failure. A scheduled restart is
+    /// announced in the output.
+    async fn finished(&mut self, exit: Option<ExitReason>) {
+        let now = tokio::time::Instant::now();
+        let ran = self.status.lock().unwrap().started_at.map(|t| t.elapsed());
+        if ran.is_some_and(|ran| ran >= RESTART_BACKOFF_RESET) {
+            self.backoff = RESTART_BACKOFF;
+        }
+        let success = exit == Some(ExitReason::Code(0));
+        let counted = !success
+            && (self.config.max_attempts.is_some() || self.config.restart != RestartPolicy::Never);
+        let state = if counted && !self.attempt_failed() {
+            ProcessState::Failed
+        } else {
+            ProcessState::Exited
+        };
+        if success && self.config.restart == RestartPolicy::Always && !self.held {
+            let delay = self.retry_delay();
+            self.retry_at = Some(tokio::time::Instant::now() + delay);
+            self.status.lock().unwrap().retrying = true;
+        }
+        self.status.lock().unwrap().mark_finished(state, exit);
+        self.record(Event::Exit(exit));
+        self.record_pgid();
+        if let Some(at) = self.retry_at {
+            let how = match exit {
+                Some(ExitReason::Code(code)) => format!("exited with code {}", code),
+                Some(signal) => format!("was killed by {}", signal),
+                None => "ended".to_string(),
+            };
+            let delay = at.saturating_duration_since(now);
+            let _ = self
+                .tx
+                .send(OutputLine::runner(format!(
+                    "process {}, restarting in {}",
+                    how,
+                    crate::summary::format_duration(delay)
+                )))
+                .await;
+        }
+    }
+
+    /// Follows the daemon: reads its PID once the pidfile is written, then
+    /// notices when it is gone, which ends the process with an unknown exit.
+    /// A pidfile not written in time is reported in the output.
+    async fn check_daemon(&mut self) {
+        let Some(daemon) = &mut self.daemon else {
+            return;
+        };
+        match daemon.pid {
+            Some(pid) if !process_alive(pid) => {
+                self.daemon = None;
+                self.child_pgid = None;
+                self.finished(None).await;
+            }
+            Some(_) => {}
+            None => {
+                let path = self
+                    .config
+                    .dir()
+                    .join(self.config.pidfile.as_deref().unwrap_or_default());
+                if let Some(pid) =
+                    read_pidfile(&path, daemon.launched).filter(|&pid| process_alive(pid))
+                {
+                    daemon.pid = Some(pid);
+                    self.child_pgid = process_group(pid);
+                    self.status.lock().unwrap().daemon_pid = Some(pid);
+                    self.record_pgid();
+                } else if Instant::now() >= daemon.deadline {
+                    self.daemon = None;
+                    let _ = self
+                        .tx
+                        .send(OutputLine::runner(format!(
+                            "no running process found in pidfile '{}'",
+                            path.display()
+                        )))
+                        .await;
+                    self.finished(Some(ExitReason::Code(0))).await;
+                }
+            }
+        }
+    }
+}
+
+/// Reads the PID in a pidfile written since `launched`.
+fn read_pidfile(path: &Path, launched: SystemTime) -> Option<i32> {
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    // Allow for file systems with coarse timestamps.
+    if modified + Duration::from_secs(1) < launched {
+        return None;
+    }
+    let contents = std::fs::read_to_string(path).ok()?;
+    contents
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+        .filter(|&pid| pid > 1)
+}
+
+/// Whether a process with this PID exists and hasn't ended.
+#[cfg(unix)]
+fn process_alive(pid: i32) -> bool {
+    use nix::errno::Errno;
+    use nix::sys::signal::kill;
+    use nix::unistd::Pid;
+    // Signal 0 only checks that the process exists.
+    if matches!(kill(Pid::from_raw(pid), None), Err(Errno::ESRCH)) {
+        return false;
+    }
+    // A zombie waiting to be reaped by its new parent has ended too.
+    #[cfg(target_os = "linux")]
+    if let Ok(stat) = std::fs::read_to_string(format!("/proc/{}/stat", pid))
+        && let Some((_, rest)) = stat.rsplit_once(')')
+    {
+        return !rest.trim_start().starts_with('Z');
+    }
+    true
+}
+
+#[cfg(not(unix))]
+fn process_alive(_pid: i32) -> bool {
+    false
+}
+
+/// Returns the process group of a daemon, unless it is the runner's own.
+#[cfg(unix)]
+fn process_group(pid: i32) -> Option<i32> {
+    use nix::unistd::{Pid, getpgid};
+    let pgid = getpgid(Some(Pid::from_raw(pid))).ok()?;
+    (pgid != getpgid(None).ok()?).then_some(pgid.as_raw())
+}
+
+#[cfg(not(unix))]
+fn process_group(_pid: i32) -> Option<i32> {
+    None
+}
+
+/// Kills a daemon and its process group, if known.
+#[cfg(unix)]
+fn kill_daemon(pid: i32, pgid: Option<i32>) {
+    use nix::sys::signal::{Signal, kill, killpg};
+    use nix::unistd::Pid;
+    if let Some(pgid) = pgid {
+        let _ = killpg(Pid::from_raw(pgid), Signal::SIGKILL);
+    }
+    let _ = kill(Pid::from_raw(pid), Signal::SIGKILL);
+}
+
+#[cfg(not(unix))]
+fn kill_daemon(_pid: i32, _pgid: Option<i32>) {}
+
+/// A Windows job object, closed when dropped.
+#[cfg(windows)]
+struct Job(winapi::um::winnt::HANDLE);
+
+// The handle is only used through the job functions, from any thread.
+#[cfg(windows)]
+unsafe impl Send for Job {}
+
+#[cfg(windows)]
+impl Drop for Job {
+    fn drop(&mut self) {
+        unsafe { winapi::um::handleapi::CloseHandle(self.0) };
+    }
+}
+
+/// Job objects of the running children, by the ID kept as their process group.
+#[cfg(windows)]
+static JOBS: Mutex<BTreeMap<i32, Job>> = Mutex::new(BTreeMap::new());
+
+/// Puts a child in a new job object, so that the processes it starts can be
+/// terminated with it, as its process group would be on Unix. Processes started
+/// before the child is assigned are left out; without a job only the child is
+/// killed.
+#[cfg(windows)]
+fn assign_job(child: &tokio::process::Child, pgid: i32) {
+    use winapi::um::jobapi2::{AssignProcessToJobObject, CreateJobObjectW};
+    let Some(process) = child.raw_handle() else {
+        return;
+    };
+    let handle = unsafe { CreateJobObjectW(std::ptr::null_mut(), std::ptr::null()) };
+    if handle.is_null() {
+        return;
+    }
+    let job = Job(handle);
+    if unsafe { AssignProcessToJobObject(job.0, process.cast()) } != 0 {
+        JOBS.lock().unwrap().insert(pgid, job);
+    }
+}
+
+/// Terminates every process in the job of a child.
+#[cfg(windows)]
+fn terminate_job(pgid: Option<i32>) {
+    if let Some(job) = pgid.and_then(|pgid| JOBS.lock().unwrap().remove(&pgid)) {
+        unsafe { winapi::um::jobapi2::TerminateJobObject(job.0, 1) };
+    }
+}
+
+/// Closes the job of a child that ended on its own, leaving the processes it
+/// started running, as on Unix.
+#[cfg(windows)]
+fn release_job(pgid: Option<i32>) {
+    if let Some(pgid) = pgid {
+        JOBS.lock().unwrap().remove(&pgid);
+    }
+}
+
+#[cfg(not(windows))]
+fn release_job(_pgid: Option<i32>) {}
+
+/// Waits for the child to exit. Never completes when there is no child,
+/// so it can sit in a `select!` next to the command channel.
+async fn wait_child(
+    child: &mut Option<tokio::process::Child>,
+) -> std::io::Result<std::process::ExitStatus> {
+    match child {
+        Some(c) => c.wait().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Completes when the next attempt after a failure is due. Never completes
+/// when none is scheduled.
+async fn retry_due(at: Option<tokio::time::Instant>) {
+    match at {
+        Some(at) => tokio::time::sleep_until(at).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Completes with a start slot once one is free, while a launch is queued.
+/// Never completes otherwise.
+async fn start_slot(limit: Option<Arc<Semaphore>>) -> OwnedSemaphorePermit {
+    match limit {
+        // The semaphore is never closed, so acquiring only fails if it were.
+        Some(limit) => match limit.acquire_owned().await {
+            Ok(permit) => permit,
+            Err(_) => std::future::pending().await,
+        },
+        None => std::future::pending().await,
+    }
+}
+
+/// Completes when the startup window of a held start slot is over. Never
+/// completes when no slot is held.
+async fn startup_elapsed(permit: &Option<(OwnedSemaphorePermit, tokio::time::Instant)>) {
+    match permit {
+        Some((_, deadline)) => tokio::time::sleep_until(*deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Completes after `every` when the usage is sampled, so that it is sampled
+/// again. Never completes otherwise.
+async fn usage_poll(every: Option<Duration>) {
+    match every {
+        Some(every) => tokio::time::sleep(every).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Completes after a while when a daemon is followed, so that it is checked
+/// again. Never completes otherwise.
+async fn daemon_poll(following: bool) {
+    if following {
+        tokio::time::sleep(DAEMON_POLL).await;
+    } else {
+        std::future::pending().await
+    }
+}
+
+/// Completes after a while when a start waits for dependencies, so that they are
+/// checked again. Never completes otherwise.
+async fn dependency_poll(waiting: bool) {
+    if waiting {
+        tokio::time::sleep(DEPENDENCY_POLL).await;
+    } else {
+        std::future::pending().await
+    }
+}
+
+/// Returns the program and arguments to execute for a process.
+///
+/// When `line_buffered` is set, the command is prefixed with `stdbuf -oL -eL` so
+/// that tools which block-buffer their output when not attached to a terminal
+/// flush every line. This only applies on Linux and when `stdbuf` is found on
+/// the `PATH`; otherwise the command is returned unchanged.
+fn command_line(command: &str, args: &[String], line_buffered: bool) -> (String, Vec<String>) {
+    if line_buffered
+        && cfg!(target_os = "linux")
+        && let Some(stdbuf) = find_in_path("stdbuf")
+    {
+        let mut wrapped = vec!["-oL".to_string(), "-eL".to_string(), command.to_string()];
+        wrapped.extend(args.iter().cloned());
+        return (stdbuf.to_string_lossy().into_owned(), wrapped);
+    }
+    (command.to_string(), args.to_vec())
+}
+
+/// Returns the program and arguments running a command line through the shell:
+/// `sh -c LINE sh ARGS...` on Unix, where the arguments are `$1`, `$2`..., or
+/// `cmd /C LINE ARGS...` on Windows.
+fn shell_command(line: &str, args: &[String]) -> (String, Vec<String>) {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut wrapped = vec![flag.to_string(), line.to_string()];
+    if !cfg!(windows) && !args.is_empty() {
+        // `$0` of the command line.
+        wrapped.push(shell.to_string());
+    }
+    wrapped.extend(args.iter().cloned());
+    (shell.to_string(), wrapped)
+}
+
+/// Settings applied to every child right before it runs its command (Unix only).
+#[derive(Default)]
+struct ChildSetup {
+    /// Niceness to run with.
+    nice: Option<i32>,
+    /// `cgroup.procs` file of the cgroup to join (Linux only).
+    cgroup_procs: Option<CString>,
+}
+
+/// Environment of a child: inherited in full by default, or, with `env_clear`,
+/// only the variables listed in `env_passthrough`, then the `env` entries of the
+/// process, which win over everything else.
+fn base_env(proc: &ProcessConfig) -> BTreeMap<OsString, OsString> {
+    let mut vars: BTreeMap<OsString, OsString> = if proc.env_clear {
+        proc.env_passthrough
+            .iter()
+            .filter_map(|name| Some((name.into(), std::env::var_os(name)?)))
+            .collect()
+    } else {
+        std::env::vars_os().collect()
+    };
+    vars.extend(proc.env.iter().map(|(k, v)| (k.into(), v.into())));
+    vars
+}
+
+/// Returns the configuration a child is spawned with, its `${VAR}` references
+/// expanded, and its environment, with the variables of the `env_file`, read
+/// again at each start, added before the `env` entries.
+///
+/// `cwd` is expanded first, as it locates the env file, so it only sees the
+/// inherited variables and `env`. The command line of a `shell` process is
+/// left to the shell to expand.
+fn prepare_child(
+    config: &ProcessConfig,
+) -> std::io::Result<(ProcessConfig, BTreeMap<OsString, OsString>)> {
+    let invalid = |e: String| std::io::Error::new(std::io::ErrorKind::InvalidInput, e);
+    let mut proc = config.clone();
+    let mut vars = base_env(config);
+    let expand = |value: &str, vars: &BTreeMap<OsString, OsString>| {
+        let lookup = |name: &str| {
+            vars.get(OsStr::new(name))
+                .map(|var| var.to_string_lossy().into_owned())
+        };
+        expand_vars(value, lookup, config.strict_env).map_err(invalid)
+    };
+    proc.cwd = match &config.cwd {
+        Some(cwd) => Some(expand_home(&expand(cwd, &vars)?)),
+        None => None,
+    };
+    if !proc.dir().is_dir() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!(
+                "working directory '{}' does not exist",
+                proc.dir().display()
+            ),
+        ));
+    }
+    if let Some(file) = &config.env_file {
+        let file_vars = read_env_file(&proc.dir().join(file))
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        vars.extend(file_vars.into_iter().map(|(k, v)| (k.into(), v.into())));
+        vars.extend(config.env.iter().map(|(k, v)| (k.into(), v.into())));
+    }
+    if !config.shell {
+        proc.command = expand(&config.command, &vars)?;
+        proc.args = config
+            .args
+            .iter()
+            .map(|arg| expand(arg, &vars))
+            .collect::<Result<_, _>>()?;
+    }
+    Ok((proc, vars))
+}
+
+/// Spawns a new process with the given command and arguments, in the working
+/// directory of the process, or that of the runner without one, and with the
+/// given environment.
 ///
 /// # Safety
 /// This function uses `pre_exec` to set the process group ID before exec'ing the child.
 /// This is required for proper process group management and signal handling.
+/// The child also joins the cgroup and takes the niceness of `setup` there, so
+/// that whatever the command forks starts inside the limits.
 ///
 /// Returns:
 /// - The spawned `tokio::process::Child`
 /// - The process group ID (pgid) as an Option<i32>
+///
+/// or the error if the process could not be spawned.
 unsafe fn spawn_child(
     command: &str,
     args: &[String],
-    cwd: &str,
-) -> (tokio::process::Child, Option<i32>) {
+    proc: &ProcessConfig,
+    env: &BTreeMap<OsString, OsString>,
+    setup: &ChildSetup,
+) -> std::io::Result<(tokio::process::Child, Option<i32>)> {
+    let mut cmd = Command::new(command);
+    cmd.env_clear().envs(env);
+    if let Some(cwd) = &proc.cwd {
+        cmd.current_dir(cwd);
+    }
     #[cfg(unix)]
     {
+        let nice = setup.nice;
+        let cgroup_procs = setup.cgroup_procs.clone();
         let spawned = unsafe {
-            Command::new(command)
-                .args(args)
-                .current_dir(cwd)
-                .stdin(Stdio::null())
+            cmd.args(args)
+                .stdin(if proc.stdin {
+                    Stdio::piped()
+                } else {
+                    Stdio::null()
+                })
                 .stdout(Stdio::piped())
                 .stderr(Stdio::piped())
-                .pre_exec(|| {
+                .pre_exec(move || {
                     libc::setpgid(0, 0);
+                    // Only async-signal-safe calls here; writing 0 moves the caller.
+                    if let Some(path) = &cgroup_procs {
+                        let fd = libc::open(path.as_ptr(), libc::O_WRONLY);
+                        if fd >= 0 {
+                            libc::write(fd, b"0".as_ptr().cast(), 1);
+                            libc::close(fd);
+                        }
+                    }
+                    if let Some(nice) = nice {
+                        libc::setpriority(libc::PRIO_PROCESS, 0, nice);
+                    }
                     Ok(())
                 })
-                .spawn()
-                .expect("Failed to start process")
+                .spawn()?
         };
         let pgid = spawned.id().map(|pid| pid as i32);
-        (spawned, pgid)
+        Ok((spawned, pgid))
     }
     #[cfg(windows)]
     {
         use winapi::um::winbase::CREATE_NO_WINDOW;
-        let spawned = Command::new(command)
+        let _ = setup;
+        let spawned = cmd
             .args(args)
-            .current_dir(cwd)
-            .stdin(Stdio::null())
+            .stdin(if proc.stdin {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .creation_flags(CREATE_NO_WINDOW)
-            .spawn()
-            .expect("Failed to start process");
-        let pgid = spawned.id().map(|pid| pid as i32); // Not used on Windows
-        (spawned, pgid)
+            .spawn()?;
+        // Stands in for the process group: the job holding the process tree
+        // is kept under this ID.
+        let pgid = spawned.id().map(|pid| pid as i32);
+        if let Some(pgid) = pgid {
+            assign_job(&spawned, pgid);
+        }
+        Ok((spawned, pgid))
     }
 }
 
-/// Spawns asynchronous tasks to read from the child's stdout and stderr, forwarding lines to the given sender.
+/// How the output readers treat the lines of a process.
+#[derive(Default)]
+struct OutputRules {
+    /// Patterns of output lines to drop.
+    ignore: Vec<Pattern>,
+    /// Pattern of the line announcing that the process is ready.
+    ready_when: Option<Pattern>,
+    /// Readiness comes from a port instead of the output lines.
+    ready_port: bool,
+    /// Format of the time each line is read at, for `timestamps`.
+    timestamp_format: Option<String>,
+    /// File every line is appended to, for `log_file`.
+    log: Option<Mutex<std::fs::File>>,
+    /// Turn ANSI escape sequences into styles.
+    ansi: bool,
+    /// Lines kept waiting for the channel, for `max_queued_lines`.
+    queue_limit: Option<usize>,
+}
+
+/// How often a readiness probe checks on a starting process.
+const PROBE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Watches the run that just started until it is ready: by connecting to its
+/// `ready_port` on 127.0.0.1 if set, or else by waiting for the output readers
+/// to tell. Once `ready_timeout_ms` has passed without it, the status records
+/// that the check failed. The probe ends with the run, and readiness is
+/// recorded with the recorder, if given with the process name.
+fn spawn_readiness_probe(
+    proc: &ProcessConfig,
+    status: &SharedStatus,
+    recorder: Option<(Arc<Recorder>, String)>,
+) {
+    let port = proc.ready_port;
+    let deadline = proc
+        .ready_timeout_ms
+        .map(|ms| Instant::now() + Duration::from_millis(ms));
+    if port.is_none() && deadline.is_none() {
+        return;
+    }
+    let status = status.clone();
+    let run = status.lock().unwrap().starts;
+    let current_run = move |status: &ProcessStatus| {
+        status.starts == run
+            && status.state == ProcessState::Running
+            && status.startup_latency.is_none()
+    };
+    tokio::spawn(async move {
+        loop {
+            if !current_run(&status.lock().unwrap()) {
+                return;
+            }
+            if let Some(port) = port
+                && let Ok(Ok(_)) = tokio::time::timeout(
+                    PROBE_INTERVAL,
+                    tokio::net::TcpStream::connect(("127.0.0.1", port)),
+                )
+                .await
+            {
+                let mut current = status.lock().unwrap();
+                if current_run(&current)
+                    && let Some(latency) = current.mark_ready(Instant::now())
+                    && let Some((recorder, name)) = &recorder
+                {
+                    recorder.record(name, &Event::Ready(latency));
+                }
+                return;
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                let mut current = status.lock().unwrap();
+                if current_run(&current) {
+                    current.ready_timed_out = true;
+                    if let Some((recorder, name)) = &recorder {
+                        recorder.record(name, &Event::ReadyTimeout);
+                    }
+                }
+                return;
+            }
+            tokio::time::sleep(PROBE_INTERVAL).await;
+        }
+    });
+}
+
+/// Spawns asynchronous tasks to read from the child's stdout and stderr, forwarding lines,
+/// tagged with their stream, to the given sender, as told by the output rules.
+/// Readiness and the lines are recorded with the recorder, if given with the
+/// process name.
 ///
 /// This function takes ownership of the child's stdout and stderr handles (if present)
 /// and spawns a task for each that reads lines and sends them to the provided channel.
 /// This avoids aliasing and undefined behavior by using `.take()` to move the handles out of the child.
-fn spawn_output_readers(child: &mut tokio::process::Child, tx: &Sender<String>) {
+fn spawn_output_readers(
+    child: &mut tokio::process::Child,
+    tx: &Sender<OutputLine>,
+    status: &SharedStatus,
+    rules: &Arc<OutputRules>,
+    recent: &Arc<OutputRing>,
+    recorder: &Option<(Arc<Recorder>, String)>,
+) -> Vec<JoinHandle<()>> {
+    let mut readers = Vec::new();
     // Take ownership of stdio handles using .take() so no aliasing or UB occurs.
     if let Some(stdout) = child.stdout.take() {
-        handle_output_owned(stdout, tx.clone());
+        readers.push(handle_output_owned(
+            stdout,
+            Stream::Stdout,
+            tx.clone(),
+            status.clone(),
+            rules.clone(),
+            recent.clone(),
+            recorder.clone(),
+        ));
     }
     if let Some(stderr) = child.stderr.take() {
-        handle_output_owned(stderr, tx.clone());
+        readers.push(handle_output_owned(
+            stderr,
+            Stream::Stderr,
+            tx.clone(),
+            status.clone(),
+            rules.clone(),
+            recent.clone(),
+            recorder.clone(),
+        ));
     }
+    readers
 }
 
 /// Reads lines from the given stream and sends them to the provided channel.
 ///
 /// This function is used by `spawn_output_readers` to asynchronously read lines from
 /// a process's stdout or stderr and forward them to the main application via a channel.
-/// Each line is trimmed of trailing newlines before sending.
-fn handle_output_owned<T>(stream: T, tx: Sender<String>)
+/// Each line is trimmed of trailing newlines and split from its hyperlinks before
+/// sending, counted in the status and kept in the ring of recent output.
+/// Lines matching one of the `ignore` patterns are discarded without being counted.
+/// The others are also appended to the log file, if there is one.
+/// The first line matching `ready_when`, or the first line at all without it, marks
+/// the process as ready, unless a `ready_port` does.
+///
+/// A carriage return also ends a line, as progress bars print one to redraw
+/// theirs: the next line is sent to replace it, and only the line left when a
+/// line feed comes, or the output ends, is counted, logged and kept. A line
+/// left unfinished for a moment, such as a prompt, is sent the same way, to
+/// be replaced once the rest of it comes.
+///
+/// The pipe is drained by one task into a queue, and a second task delivers
+/// the queued lines to the bounded channel. A slow consumer thus only delays
+/// delivery: the child never blocks writing to a full pipe. The queue grows as
+/// needed, or with `max_queued_lines` drops its oldest lines, which is told in
+/// the output. Bytes that aren't valid UTF-8 are replaced rather than ending
+/// the reading. Returns the task draining the pipe, which ends with the output.
+fn handle_output_owned<T>(
+    stream: T,
+    source: Stream,
+    tx: Sender<OutputLine>,
+    status: SharedStatus,
+    rules: Arc<OutputRules>,
+    recent: Arc<OutputRing>,
+    recorder: Option<(Arc<Recorder>, String)>,
+) -> JoinHandle<()>
 where
     T: tokio::io::AsyncRead + Unpin + Send + 'static,
 {
     let mut reader = tokio::io::BufReader::new(stream);
+    let queue = Arc::new(LineQueue::new(rules.queue_limit));
+    let queued = queue.clone();
     tokio::spawn(async move {
-        let mut line = String::new();
-        while reader.read_line(&mut line).await.unwrap_or(0) > 0 {
-            let _ = tx.send(line.trim_end().to_string()).await;
-            line.clear();
+        while let Some((output, dropped)) = queued.pop().await {
+            if dropped > 0 {
+                let notice = OutputLine::runner(format!(
+                    "{} lines dropped, the display fell behind",
+                    dropped
+                ));
+                if tx.send(notice).await.is_err() {
+                    break;
+                }
+            }
+            if tx.send(output).await.is_err() {
+                break;
+            }
         }
+        queued.close();
     });
+    tokio::spawn(async move {
+        let mut segment = Vec::new();
+        // The last line shown, if a carriage return ended it: the next one
+        // overwrites it, unless a line feed comes first and keeps it as it is.
+        let mut overwritten: Option<OutputLine> = None;
+        while let Ok(end) = read_segment(&mut reader, &mut segment).await {
+            if end == SegmentEnd::Eof && segment.is_empty() {
+                break;
+            }
+            {
+                let mut status = status.lock().unwrap();
+                status.last_output = Some(Instant::now());
+                status.idle = false;
+            }
+            // Shown now and overwritten by what follows, like the line left
+            // unfinished by a pause.
+            let carriage = end == SegmentEnd::CarriageReturn;
+            let unfinished = carriage || end == SegmentEnd::Pause;
+            if segment.is_empty() && (carriage || overwritten.is_some()) {
+                if !carriage && let Some(line) = overwritten.take() {
+                    keep_line(&status, &rules, &recorder, &recent, &line);
+                }
+                continue;
+            }
+            let timestamp = rules
+                .timestamp_format
+                .as_ref()
+                .map(|format| chrono::Local::now().format(format).to_string());
+            let (text, mut links) =
+                extract_hyperlinks(String::from_utf8_lossy(&segment).trim_end());
+            // After a pause the rest of the line is still to come.
+            if end != SegmentEnd::Pause {
+                segment.clear();
+            }
+            let (text, mut styles) = if rules.ansi {
+                extract_styles(&text, &mut links)
+            } else {
+                (text, Vec::new())
+            };
+            // Shown as visible symbols, as the terminal would act on raw control characters.
+            let text = show_controls(&text, &mut links, &mut styles);
+            if rules.ignore.iter().any(|pattern| pattern.is_match(&text)) {
+                continue;
+            }
+            {
+                let mut current = status.lock().unwrap();
+                if !rules.ready_port
+                    && rules.ready_when.as_ref().is_none_or(|p| p.is_match(&text))
+                    && let Some(latency) = current.mark_ready(Instant::now())
+                    && let Some((recorder, name)) = &recorder
+                {
+                    recorder.record(name, &Event::Ready(latency));
+                }
+            }
+            let output = OutputLine {
+                links,
+                styles,
+                timestamp,
+                replaces: overwritten.is_some(),
+                ..OutputLine::new(source, text)
+            };
+            if unfinished {
+                overwritten = Some(output.clone());
+            } else {
+                overwritten = None;
+                keep_line(&status, &rules, &recorder, &recent, &output);
+            }
+            // Once the delivery task is gone, keep draining so the child doesn't block.
+            queue.push(output);
+        }
+        if let Some(line) = overwritten {
+            keep_line(&status, &rules, &recorder, &recent, &line);
+        }
+        queue.close();
+    })
+}
+
+/// How a segment of output read by `read_segment` ends.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SegmentEnd {
+    LineFeed,
+    CarriageReturn,
+    /// The process wrote part of a line and nothing else for `PARTIAL_LINE_DELAY`.
+    Pause,
+    Eof,
+}
+
+/// Time a partly written line, such as a prompt, waits for the rest before it
+/// is shown.
+const PARTIAL_LINE_DELAY: Duration = Duration::from_millis(100);
+
+/// Reads bytes into `segment` up to the next line feed or carriage return,
+/// which is consumed, or until the process pauses in the middle of a line
+/// after adding to it.
+async fn read_segment<R>(reader: &mut R, segment: &mut Vec<u8>) -> std::io::Result<SegmentEnd>
+where
+    R: tokio::io::AsyncBufRead + Unpin,
+{
+    let start = segment.len();
+    loop {
+        let available = if segment.len() == start {
+            reader.fill_buf().await?
+        } else {
+            match tokio::time::timeout(PARTIAL_LINE_DELAY, reader.fill_buf()).await {
+                Ok(available) => available?,
+                Err(_) => return Ok(SegmentEnd::Pause),
+            }
+        };
+        if available.is_empty() {
+            return Ok(SegmentEnd::Eof);
+        }
+        match available.iter().position(|&b| b == b'\n' || b == b'\r') {
+            Some(i) => {
+                let end = if available[i] == b'\n' {
+                    SegmentEnd::LineFeed
+                } else {
+                    SegmentEnd::CarriageReturn
+                };
+                segment.extend_from_slice(&available[..i]);
+                reader.consume(i + 1);
+                return Ok(end);
+            }
+            None => {
+                let read = available.len();
+                segment.extend_from_slice(available);
+                reader.consume(read);
+            }
+        }
+    }
+}
+
+/// Records a line that is done being written: counts it in the status, unless
+/// it is a note, and appends it to the log file, the event stream and the
+/// recent output.
+fn keep_line(
+    status: &SharedStatus,
+    rules: &OutputRules,
+    recorder: &Option<(Arc<Recorder>, String)>,
+    recent: &OutputRing,
+    line: &OutputLine,
+) {
+    if !line.note {
+        status.lock().unwrap().lines += 1;
+    }
+    if let Some(log) = &rules.log {
+        let entry = match &line.timestamp {
+            Some(timestamp) => format!("{} {}\n", timestamp, line.text),
+            None => format!("{}\n", line.text),
+        };
+        // Unbuffered, so each line is on disk right away for `tail -f`.
+        let _ = std::io::Write::write_all(&mut *log.lock().unwrap(), entry.as_bytes());
+    }
+    if let Some((recorder, name)) = recorder {
+        recorder.output(name, line);
+    }
+    recent.push(line.clone());
 }
 
 /// Stops the given child process and its process group, if running.
 ///
 /// This function:
-/// - Sends a SIGKILL to the process group (if available) to ensure all subprocesses are killed.
+/// - Sends a SIGKILL to the process group (if available) to ensure all subprocesses are killed;
+///   on Windows, terminates the job object the child was put in instead.
 /// - Calls `.kill()` on the main child process to ensure it is terminated.
 /// - Cleans up the process handle and process group ID.
-fn stop_child(child: &mut Option<tokio::process::Child>, child_pgid: &mut Option<i32>) {
+///
+/// Returns true if there was a running child to stop.
+async fn stop_child(
+    child: &mut Option<tokio::process::Child>,
+    child_pgid: &mut Option<i32>,
+) -> bool {
     #[cfg(unix)]
     {
         use nix::sys::signal::{self, Signal};
@@ -209,15 +2376,513 @@ fn stop_child(child: &mut Option<tokio::process::Child>, child_pgid: &mut Option
             if let Some(pgid) = child_pgid.take() {
                 let _ = signal::killpg(Pid::from_raw(pgid), Signal::SIGKILL);
             }
-            let _ = futures::executor::block_on(c.kill());
+            let _ = c.kill().await;
+            return true;
         }
+        false
     }
     #[cfg(windows)]
     {
         if let Some(mut c) = child.take() {
-            let _ = futures::executor::block_on(c.kill());
+            terminate_job(child_pgid.take());
+            let _ = c.kill().await;
+            return true;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Path in the temporary directory that no other test uses.
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("starter-{}-{}", std::process::id(), name))
+    }
+
+    fn process(name: &str, cmd: &str) -> ProcessConfig {
+        ProcessConfig {
+            name: name.to_string(),
+            command: cmd.to_string(),
+            shell: true,
+            ..Default::default()
+        }
+    }
+
+    /// A supervisor of `proc` and the receiver of its output.
+    fn supervisor(proc: ProcessConfig) -> (Supervisor, Receiver<OutputLine>) {
+        let (tx, rx) = mpsc::channel(DEFAULT_OUTPUT_CAPACITY);
+        (
+            Supervisor::new(proc, false, tx, SharedStatus::default()),
+            rx,
+        )
+    }
+
+    #[test]
+    fn notes_are_logged_without_counting_as_output() {
+        let path = temp_path("note.log");
+        let (mut supervisor, _rx) = supervisor(process("api", "true"));
+        let file = std::fs::File::create(&path).unwrap();
+        supervisor.rules = Arc::new(OutputRules {
+            log: Some(Mutex::new(file)),
+            ..OutputRules::default()
+        });
+        supervisor.recent = Arc::new(OutputRing::new(10));
+        supervisor.note("── 12:00:00 note: reproducing ──".to_string());
+        let logged = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(logged, "── 12:00:00 note: reproducing ──\n");
+        let recent = supervisor.recent.last(1);
+        assert!(recent[0].note);
+        assert_eq!(supervisor.status.lock().unwrap().lines, 0);
+    }
+
+    fn config(yaml: &str) -> Config {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[tokio::test]
+    async fn reloads_restart_gracefully_and_reach_the_manager() {
+        let marker = temp_path("reload-term");
+        let script = format!(
+            "trap 'echo term >> {}; exit 0' TERM; while true; do sleep 0.05; done",
+            marker.display()
+        );
+        let config = config(&format!(
+            "processes: [{{name: api, shell: true, command: \"{}\"}}]",
+            script
+        ));
+        let (handles, mut manager) = spawn_process(&config, None).await.unwrap();
+        let api = &handles[0];
+        api.control.send(ProcessCommand::Start).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let (reply, outcome) = oneshot::channel();
+        let dir = std::env::temp_dir().to_string_lossy().into_owned();
+        let update = CommandUpdate {
+            cwd: Some(dir.clone()),
+            ..Default::default()
+        };
+        api.control
+            .send(ProcessCommand::Reload { update, reply })
+            .await
+            .unwrap();
+        outcome.await.unwrap().unwrap();
+        let status = api.status.lock().unwrap().clone();
+        assert_eq!(status.state, ProcessState::Running);
+        assert_eq!(status.starts, 2);
+        // The first run had the chance to exit on SIGTERM.
+        assert_eq!(std::fs::read_to_string(&marker).unwrap(), "term\n");
+        std::fs::remove_file(&marker).unwrap();
+        assert_eq!(manager.configs[0].lock().unwrap().cwd, Some(dir));
+        // The file still has no `cwd`, so applying it again restores that.
+        let changes = manager.apply_config(&config);
+        assert_eq!(changes.changed, ["api"]);
+        manager.stop_all();
+    }
+
+    #[tokio::test]
+    async fn dependencies_without_a_readiness_check_are_ready_once_running() {
+        let config = config(
+            "processes:
+  - {name: db, command: sleep, args: ['30']}
+  - {name: api, command: sleep, args: ['30'], depends_on: [db]}",
+        );
+        let (handles, mut manager) = spawn_process(&config, None).await.unwrap();
+        for handle in &handles {
+            handle.control.send(ProcessCommand::Start).await.unwrap();
         }
-        // No process group support on Windows; only the main process is killed.
-        let _ = child_pgid.take();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let api = handles[1].status.lock().unwrap().clone();
+        assert_eq!(api.state, ProcessState::Running);
+        assert!(api.waiting_for.is_empty());
+        manager.stop_all();
+    }
+
+    #[tokio::test]
+    async fn starts_beyond_max_concurrent_starts_wait_and_can_be_stopped() {
+        let config = config(
+            "max_concurrent_starts: 2
+processes:
+  - {name: a, command: sleep, args: ['30']}
+  - {name: b, command: sleep, args: ['30']}
+  - {name: c, command: sleep, args: ['30']}
+  - {name: d, command: sleep, args: ['30']}",
+        );
+        let (handles, mut manager) = spawn_process(&config, None).await.unwrap();
+        for handle in &handles {
+            handle.control.send(ProcessCommand::Start).await.unwrap();
+        }
+        let running = || {
+            handles
+                .iter()
+                .filter(|h| h.status.lock().unwrap().state == ProcessState::Running)
+                .count()
+        };
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(running(), 2);
+        // A queued start is still stopped on request.
+        let queued = handles
+            .iter()
+            .find(|h| h.status.lock().unwrap().state != ProcessState::Running)
+            .unwrap();
+        queued.control.send(ProcessCommand::Stop).await.unwrap();
+        tokio::time::sleep(STARTUP_WINDOW + Duration::from_millis(300)).await;
+        assert_eq!(running(), 3);
+        assert_eq!(queued.status.lock().unwrap().starts, 0);
+        manager.stop_all();
+    }
+
+    #[test]
+    fn line_buffered_commands_run_through_stdbuf() {
+        let args = vec!["-c".to_string(), "echo hi".to_string()];
+        assert_eq!(
+            command_line("sh", &args, false),
+            ("sh".to_string(), args.clone())
+        );
+        let (program, wrapped) = command_line("sh", &args, true);
+        match find_in_path("stdbuf").filter(|_| cfg!(target_os = "linux")) {
+            Some(stdbuf) => {
+                assert_eq!(program, stdbuf.to_string_lossy());
+                assert_eq!(wrapped, ["-oL", "-eL", "sh", "-c", "echo hi"]);
+            }
+            None => assert_eq!((program, wrapped), ("sh".to_string(), args)),
+        }
+    }
+
+    fn pattern(source: &str) -> Pattern {
+        serde_yaml::from_value(serde_yaml::Value::String(source.to_string())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn ignored_lines_are_dropped_and_others_pass() {
+        let (tx, mut rx) = mpsc::channel(DEFAULT_OUTPUT_CAPACITY);
+        let status = SharedStatus::default();
+        let rules = Arc::new(OutputRules {
+            ignore: vec![pattern("heartbeat"), pattern(r"^GET /health")],
+            ..OutputRules::default()
+        });
+        let output: &'static [u8] = b"starting\nheartbeat 1\nGET /health 200\nGET /users 200\n";
+        handle_output_owned(
+            output,
+            Stream::Stdout,
+            tx,
+            status.clone(),
+            rules,
+            Arc::new(OutputRing::new(10)),
+            None,
+        )
+        .await
+        .unwrap();
+        let mut texts = Vec::new();
+        while let Some(line) = rx.recv().await {
+            texts.push(line.text);
+        }
+        assert_eq!(texts, ["starting", "GET /users 200"]);
+        assert_eq!(status.lock().unwrap().lines, 2);
+    }
+
+    #[test]
+    fn readiness_records_the_latency_of_the_start_once() {
+        let mut status = ProcessStatus::default();
+        assert_eq!(status.mark_ready(Instant::now()), None);
+        status.mark_started();
+        let started = status.started_at.unwrap();
+        let ready = started + Duration::from_millis(1500);
+        assert_eq!(status.mark_ready(ready), Some(Duration::from_millis(1500)));
+        assert_eq!(status.mark_ready(ready + Duration::from_secs(1)), None);
+        assert_eq!(status.startup_latency, Some(Duration::from_millis(1500)));
+        status.mark_started();
+        assert_eq!(status.startup_latency, None);
+    }
+
+    #[test]
+    fn output_ring_keeps_the_last_lines() {
+        let ring = OutputRing::new(3);
+        for i in 1..=5 {
+            ring.push(OutputLine::new(Stream::Stdout, i.to_string()));
+        }
+        let texts = |lines: Vec<OutputLine>| -> Vec<String> {
+            lines.into_iter().map(|line| line.text).collect()
+        };
+        assert_eq!(texts(ring.last(2)), ["4", "5"]);
+        assert_eq!(texts(ring.last(10)), ["3", "4", "5"]);
+        let empty = OutputRing::new(0);
+        empty.push(OutputLine::new(Stream::Stdout, "dropped"));
+        assert!(empty.last(1).is_empty());
+    }
+
+    #[tokio::test]
+    async fn recent_output_reads_back_the_last_lines_of_a_process() {
+        let config = config(
+            "
+            recent_output: 5
+            processes: [{name: counter, command: seq, args: ['1', '20']}]
+            ",
+        );
+        let (handles, mut manager) = spawn_process(&config, None).await.unwrap();
+        handles[0]
+            .control
+            .send(ProcessCommand::Start)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let lines = manager.recent_output("counter", 3).unwrap();
+        let texts: Vec<&str> = lines.iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(texts, ["18", "19", "20"]);
+        assert_eq!(manager.recent_output("counter", 10).unwrap().len(), 5);
+        assert!(manager.recent_output("missing", 3).is_none());
+        manager.stop_all();
+    }
+
+    #[tokio::test]
+    async fn starts_report_the_dependencies_they_wait_for() {
+        let config = config(
+            "processes:
+  - {name: db, shell: true, command: 'sleep 0.6; echo ready; sleep 30', ready_when: ready}
+  - {name: cache, command: sleep, args: ['30']}
+  - {name: api, command: sleep, args: ['30'], depends_on: [db, cache]}",
+        );
+        let (handles, mut manager) = spawn_process(&config, None).await.unwrap();
+        for handle in &handles {
+            handle.control.send(ProcessCommand::Start).await.unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let api = handles[2].status.lock().unwrap().clone();
+        assert_eq!(api.state, ProcessState::Stopped);
+        assert_eq!(api.waiting_for, ["db"]);
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+        let api = handles[2].status.lock().unwrap().clone();
+        assert_eq!(api.state, ProcessState::Running);
+        assert!(api.waiting_for.is_empty());
+        manager.stop_all();
+    }
+
+    #[test]
+    fn failed_attempts_stop_at_max_attempts_until_a_manual_start() {
+        let (mut supervisor, _rx) = supervisor(ProcessConfig {
+            max_attempts: Some(3),
+            ..process("api", "exit 1")
+        });
+        assert!(supervisor.attempt_failed());
+        assert!(supervisor.attempt_failed());
+        assert!(!supervisor.attempt_failed());
+        {
+            let status = supervisor.status.lock().unwrap();
+            assert_eq!(status.attempts, 3);
+            assert!(!status.retrying);
+        }
+        supervisor.reset_attempts();
+        assert_eq!(supervisor.status.lock().unwrap().attempts, 0);
+        assert!(supervisor.attempt_failed());
+        assert!(supervisor.status.lock().unwrap().retrying);
+    }
+
+    #[tokio::test]
+    async fn a_failing_process_gives_up_after_max_attempts() {
+        let config =
+            config("processes: [{name: api, shell: true, command: 'exit 1', max_attempts: 2}]");
+        let (handles, mut manager) = spawn_process(&config, None).await.unwrap();
+        let api = &handles[0];
+        api.control.send(ProcessCommand::Start).await.unwrap();
+        tokio::time::sleep(RETRY_DELAY + Duration::from_millis(500)).await;
+        let status = api.status.lock().unwrap().clone();
+        assert_eq!(status.state, ProcessState::Failed);
+        assert_eq!((status.starts, status.attempts), (2, 2));
+        assert!(!status.retrying);
+        api.control.send(ProcessCommand::Start).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let status = api.status.lock().unwrap().clone();
+        assert_eq!((status.starts, status.attempts), (3, 1));
+        assert!(status.retrying);
+        manager.stop_all();
+    }
+
+    #[test]
+    fn env_clear_passes_only_the_allowed_variables() {
+        let config = config(
+            "processes:
+  - name: api
+    command: echo
+    args: ['${GREETING}']
+    env_clear: true
+    env_passthrough: [PATH, STARTER_TEST_UNSET]
+    env: {GREETING: hi}",
+        );
+        let (proc, vars) = prepare_child(&config.processes[0]).unwrap();
+        let names: Vec<&OsStr> = vars.keys().map(OsString::as_os_str).collect();
+        assert_eq!(names, ["GREETING", "PATH"]);
+        assert_eq!(vars[OsStr::new("PATH")], std::env::var_os("PATH").unwrap());
+        assert_eq!(proc.args, ["hi"]);
+        let inherited = ProcessConfig {
+            env_clear: false,
+            ..config.processes[0].clone()
+        };
+        let (_, vars) = prepare_child(&inherited).unwrap();
+        assert_eq!(vars.len(), std::env::vars_os().count() + 1);
+    }
+
+    #[tokio::test]
+    async fn line_queue_drops_its_oldest_lines_at_the_limit() {
+        let queue = LineQueue::new(Some(3));
+        for i in 1..=5 {
+            queue.push(OutputLine::new(Stream::Stdout, i.to_string()));
+        }
+        queue.close();
+        queue.push(OutputLine::new(Stream::Stdout, "after close"));
+        let mut popped = Vec::new();
+        while let Some((line, dropped)) = queue.pop().await {
+            popped.push((line.text, dropped));
+        }
+        let expected = [("3", 2), ("4", 0), ("5", 0)].map(|(text, n)| (text.to_string(), n));
+        assert_eq!(popped, expected);
+    }
+
+    #[tokio::test]
+    async fn a_slow_consumer_never_blocks_the_reader() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let rules = Arc::new(OutputRules {
+            queue_limit: Some(10),
+            ..OutputRules::default()
+        });
+        let output: Vec<u8> = (1..=1000)
+            .map(|i| format!("{}\n", i))
+            .collect::<String>()
+            .into();
+        let reader = handle_output_owned(
+            std::io::Cursor::new(output),
+            Stream::Stdout,
+            tx,
+            SharedStatus::default(),
+            rules,
+            Arc::new(OutputRing::new(10)),
+            None,
+        );
+        // Nothing is received until the whole output was read.
+        tokio::time::timeout(Duration::from_secs(5), reader)
+            .await
+            .unwrap()
+            .unwrap();
+        let mut texts = Vec::new();
+        while let Some(line) = rx.recv().await {
+            texts.push(line.text);
+        }
+        assert!(
+            texts
+                .iter()
+                .any(|text| text.ends_with("lines dropped, the display fell behind"))
+        );
+        assert_eq!(texts.last().unwrap(), "1000");
+        assert!(texts.len() <= 13, "{} lines delivered", texts.len());
+    }
+
+    #[tokio::test]
+    async fn a_crash_after_a_manual_stop_is_not_restarted() {
+        let config = config(
+            "processes:
+  - name: api
+    shell: true
+    command: \"trap 'exit 1' TERM; while true; do sleep 0.05; done\"
+    restart: always",
+        );
+        let (handles, mut manager) = spawn_process(&config, None).await.unwrap();
+        let api = &handles[0];
+        api.control.send(ProcessCommand::Start).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        api.control.send(ProcessCommand::Stop).await.unwrap();
+        tokio::time::sleep(RESTART_BACKOFF * 2).await;
+        let status = api.status.lock().unwrap().clone();
+        assert_ne!(status.state, ProcessState::Running);
+        assert_eq!(status.starts, 1);
+        assert!(!status.retrying);
+        manager.stop_all();
+    }
+
+    #[tokio::test]
+    async fn a_manual_restart_after_giving_up_starts_afresh() {
+        let config = config(
+            "processes:
+  - {name: api, shell: true, command: 'exit 1', restart: on_failure, max_attempts: 2}",
+        );
+        let (handles, mut manager) = spawn_process(&config, None).await.unwrap();
+        let api = &handles[0];
+        api.control.send(ProcessCommand::Start).await.unwrap();
+        tokio::time::sleep(RESTART_BACKOFF + Duration::from_millis(400)).await;
+        let status = api.status.lock().unwrap().clone();
+        assert_eq!(status.state, ProcessState::Failed);
+        assert_eq!((status.starts, status.attempts), (2, 2));
+        assert!(!status.retrying);
+        api.control
+            .send(ProcessCommand::Restart { cascade: true })
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let status = api.status.lock().unwrap().clone();
+        assert_eq!((status.starts, status.attempts), (3, 1));
+        assert!(status.retrying);
+        manager.stop_all();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn a_daemonizing_command_is_followed_through_its_pidfile() {
+        let dir = temp_path("daemon");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("daemonize.sh"),
+            "sleep 30 </dev/null >/dev/null 2>&1 &\necho $! > daemon.pid\n",
+        )
+        .unwrap();
+        let config = config(&format!(
+            "processes: [{{name: daemon, command: sh, args: [daemonize.sh], pidfile: daemon.pid, cwd: '{}'}}]",
+            dir.display()
+        ));
+        let (handles, mut manager) = spawn_process(&config, None).await.unwrap();
+        let daemon = &handles[0];
+        let pidfile = || {
+            let contents = std::fs::read_to_string(dir.join("daemon.pid")).unwrap();
+            contents.trim().parse::<i32>().unwrap()
+        };
+        daemon.control.send(ProcessCommand::Start).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(800)).await;
+        let status = daemon.status.lock().unwrap().clone();
+        assert_eq!(status.state, ProcessState::Running);
+        let pid = pidfile();
+        assert_eq!(status.daemon_pid, Some(pid));
+        // The daemon ending ends the process.
+        kill_daemon(pid, None);
+        tokio::time::sleep(Duration::from_millis(800)).await;
+        let status = daemon.status.lock().unwrap().clone();
+        assert_eq!(status.state, ProcessState::Exited);
+        assert_eq!(status.daemon_pid, None);
+        // Stopping the process kills the daemon.
+        daemon.control.send(ProcessCommand::Start).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(800)).await;
+        let pid = pidfile();
+        assert_eq!(daemon.status.lock().unwrap().daemon_pid, Some(pid));
+        daemon.control.send(ProcessCommand::Stop).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(!process_alive(pid));
+        assert_eq!(daemon.status.lock().unwrap().state, ProcessState::Stopped);
+        manager.stop_all();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn reloads_take_a_relative_cwd_from_the_config_directory() {
+        let (mut supervisor, _rx) = supervisor(process("api", "sleep 30"));
+        supervisor.config_dir = std::env::temp_dir();
+        let update = CommandUpdate {
+            command: Some("true".to_string()),
+            args: Some(vec!["--now".to_string()]),
+            cwd: Some(".".to_string()),
+        };
+        supervisor.reload(update).await.unwrap();
+        let expected = std::env::temp_dir().join(".");
+        assert_eq!(
+            supervisor.config.cwd,
+            Some(expected.to_string_lossy().into_owned())
+        );
+        assert_eq!(supervisor.status.lock().unwrap().command, "true --now");
     }
 }
@@ -0,0 +1,55 @@
+use crate::line_file::LineFile;
+use crate::process::{ExitReason, OutputLine};
+use crate::stats::Event;
+use crate::summary::format_duration;
+use std::io;
+
+/// Appends the output lines of every process to a single file, interleaved in
+/// the order they arrive, along with the lifecycle events, for `--log-all`.
+///
+/// Each line starts with the local time with milliseconds and the name of the
+/// process, e.g. `2024-05-01 12:00:00.123 [api] listening on :8080`. Events
+/// are told the same way after `[runner]`, such as `[api] [runner] exited with
+/// code 1`.
+pub struct SessionLog {
+    file: LineFile,
+}
+
+impl SessionLog {
+    /// Opens the file for appending, creating it if needed.
+    pub fn create(path: &str) -> io::Result<Self> {
+        Ok(SessionLog {
+            file: LineFile::open(path)?,
+        })
+    }
+
+    /// Writes a lifecycle event of the named process.
+    pub fn record(&self, process: &str, event: &Event) {
+        let text = match event {
+            Event::Start => "started".to_string(),
+            Event::Failed => "failed to start".to_string(),
+            Event::Ready(latency) => format!("ready after {}", format_duration(*latency)),
+            Event::ReadyTimeout => "not ready in time".to_string(),
+            Event::Restart => "restarting".to_string(),
+            Event::Stop => "stopped".to_string(),
+            Event::Exit(Some(ExitReason::Code(code))) => format!("exited with code {}", code),
+            Event::Exit(Some(signal)) => format!("killed by {}", signal),
+            Event::Exit(None) => "exited".to_string(),
+        };
+        self.write(process, &format!("[runner] {}", text));
+    }
+
+    /// Writes a line of output of the named process.
+    pub fn output(&self, process: &str, line: &OutputLine) {
+        self.write(process, &line.text);
+    }
+
+    fn write(&self, process: &str, text: &str) {
+        self.file.write_line(&format!(
+            "{} [{}] {}",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+            process,
+            text
+        ));
+    }
+}
@@ -0,0 +1,60 @@
+use crate::process::{OutputLine, ProcessStatus};
+use std::{fs, io, path::PathBuf};
+
+/// Contents of one pane, as written to a snapshot.
+pub struct PaneDump<'a> {
+    pub name: &'a str,
+    /// Command line the process runs; `None` for panes not backed by a process.
+    pub command: Option<String>,
+    pub status: Option<ProcessStatus>,
+    pub lines: &'a [OutputLine],
+}
+
+/// Formats a pane as a section: a header with the process name, state and exit
+/// code, followed by its whole buffer, with the timestamps of the lines if any.
+pub fn format_pane(pane: &PaneDump) -> String {
+    let mut header = format!("==> {}", pane.name);
+    if let Some(status) = &pane.status {
+        header.push_str(&format!(" [{}", status.state));
+        if let Some(exit) = status.exit {
+            header.push_str(&format!(", exit {}", exit));
+        }
+        header.push(']');
+    }
+    let mut out = format!("{} ({} lines) <==\n", header, pane.lines.len());
+    for line in pane.lines {
+        if let Some(timestamp) = &line.timestamp {
+            out.push_str(timestamp);
+            out.push(' ');
+        }
+        out.push_str(&line.text);
+        out.push('\n');
+    }
+    out
+}
+
+/// Builds a report with every pane: first the configuration file and the command
+/// of each process, then one section per pane.
+pub fn format_snapshot(config_path: &str, panes: &[PaneDump]) -> String {
+    let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+    let mut out = format!("Snapshot taken {}\nConfiguration: {}\n\n", now, config_path);
+    for pane in panes {
+        if let Some(command) = &pane.command {
+            out.push_str(&format!("  {}: {}\n", pane.name, command));
+        }
+    }
+    for pane in panes {
+        out.push('\n');
+        out.push_str(&format_pane(pane));
+    }
+    out
+}
+
+/// Writes a snapshot of all panes to a timestamped file in the current
+/// directory and returns its path.
+pub fn write_snapshot(config_path: &str, panes: &[PaneDump]) -> io::Result<PathBuf> {
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let path = PathBuf::from(format!("starter-snapshot-{}.txt", stamp));
+    fs::write(&path, format_snapshot(config_path, panes))?;
+    Ok(path)
+}
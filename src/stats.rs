@@ -0,0 +1,177 @@
+use crate::events::EventStream;
+use crate::line_file::LineFile;
+use crate::process::{ExitReason, OutputLine};
+use crate::session_log::SessionLog;
+use std::{io, time::Duration};
+
+/// Columns of the stats file, in order. New columns are only ever added at the end.
+const HEADER: &str = "timestamp,process,event,exit,ready_ms";
+
+/// A lifecycle event of a process, as recorded in the stats file.
+pub enum Event {
+    /// The process was spawned.
+    Start,
+    /// The process could not be spawned.
+    Failed,
+    /// The process became ready, after the given time since it started.
+    Ready(Duration),
+    /// The process didn't become ready within `ready_timeout_ms`.
+    ReadyTimeout,
+    /// The user asked for a restart.
+    Restart,
+    /// The process was killed by a stop.
+    Stop,
+    /// The process ended on its own, with how it ended if known.
+    Exit(Option<ExitReason>),
+}
+
+impl Event {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::Start => "start",
+            Event::Failed => "failed",
+            Event::Ready(_) => "ready",
+            Event::ReadyTimeout => "ready_timeout",
+            Event::Restart => "restart",
+            Event::Stop => "stop",
+            Event::Exit(_) => "exit",
+        }
+    }
+}
+
+/// Appends a CSV row per lifecycle event of every process, for `--stats-csv`.
+///
+/// Each row has the local time with milliseconds, the process name, the event,
+/// the exit code or signal name for `exit` events and the time to become ready
+/// in milliseconds for `ready` events; cells that don't apply are empty. The
+/// header is written when the file is new or empty.
+pub struct StatsFile {
+    file: LineFile,
+}
+
+impl StatsFile {
+    /// Opens the file for appending, creating it if needed.
+    pub fn create(path: &str) -> io::Result<Self> {
+        let file = LineFile::open(path)?;
+        if file.is_empty()? {
+            file.write_line(HEADER);
+        }
+        Ok(StatsFile { file })
+    }
+
+    /// Appends a row for an event of the named process.
+    pub fn record(&self, process: &str, event: &Event) {
+        let now = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f%:z");
+        let exit = match event {
+            Event::Exit(Some(exit)) => exit.to_string(),
+            _ => String::new(),
+        };
+        let ready = match event {
+            Event::Ready(latency) => latency.as_millis().to_string(),
+            _ => String::new(),
+        };
+        self.file.write_line(&format!(
+            "{},{},{},{},{}",
+            now,
+            quote(process),
+            event.name(),
+            quote(&exit),
+            ready
+        ));
+    }
+}
+
+/// Quotes a CSV cell if it holds a comma, a quote or a line break.
+fn quote(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+/// Where the lifecycle events of the processes go: any of the stats file, the
+/// event stream and the session log.
+pub struct Recorder {
+    stats: Option<StatsFile>,
+    events: Option<EventStream>,
+    session_log: Option<SessionLog>,
+}
+
+impl Recorder {
+    /// Returns `None` when there is nowhere to record events.
+    pub fn new(
+        stats: Option<StatsFile>,
+        events: Option<EventStream>,
+        session_log: Option<SessionLog>,
+    ) -> Option<Self> {
+        (stats.is_some() || events.is_some() || session_log.is_some()).then_some(Recorder {
+            stats,
+            events,
+            session_log,
+        })
+    }
+
+    /// Records an event of the named process.
+    pub fn record(&self, process: &str, event: &Event) {
+        if let Some(stats) = &self.stats {
+            stats.record(process, event);
+        }
+        if let Some(events) = &self.events {
+            events.record(process, event);
+        }
+        if let Some(log) = &self.session_log {
+            log.record(process, event);
+        }
+    }
+
+    /// Records a line of output of the named process; the stats file doesn't
+    /// take these.
+    pub fn output(&self, process: &str, line: &OutputLine) {
+        if let Some(events) = &self.events {
+            events.output(process, line);
+        }
+        if let Some(log) = &self.session_log {
+            log.output(process, line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_written_as_csv_rows() {
+        let path = std::env::temp_dir().join(format!("starter-{}-stats.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        let stats = StatsFile::create(path).unwrap();
+        let name = "api, \"v2\"";
+        stats.record(name, &Event::Start);
+        stats.record(name, &Event::Ready(Duration::from_millis(1500)));
+        stats.record(name, &Event::Exit(Some(ExitReason::Code(1))));
+        drop(stats);
+        // Opening the file again doesn't repeat the header.
+        StatsFile::create(path).unwrap().record("db", &Event::Stop);
+        let contents = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let mut rows = contents.lines();
+        assert_eq!(rows.next(), Some(HEADER));
+        let cells: Vec<String> = rows
+            .map(|row| {
+                let (time, rest) = row.split_once(',').unwrap();
+                assert!(chrono::DateTime::parse_from_str(time, "%Y-%m-%dT%H:%M:%S%.3f%:z").is_ok());
+                rest.to_string()
+            })
+            .collect();
+        assert_eq!(
+            cells,
+            [
+                "\"api, \"\"v2\"\"\",start,,",
+                "\"api, \"\"v2\"\"\",ready,,1500",
+                "\"api, \"\"v2\"\"\",exit,1,",
+                "db,stop,,",
+            ]
+        );
+    }
+}
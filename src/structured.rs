@@ -0,0 +1,47 @@
+use serde_json::Value;
+
+/// Formats a JSON log line as the values of the selected fields, in the given
+/// order and separated by spaces. Strings are shown without quotes, other values
+/// as compact JSON, and missing fields are skipped.
+///
+/// Returns `None` when the line is not a JSON object, so it can be shown raw.
+pub fn format_json_line(line: &str, fields: &[String]) -> Option<String> {
+    let Value::Object(object) = serde_json::from_str::<Value>(line.trim()).ok()? else {
+        return None;
+    };
+    let values: Vec<String> = fields
+        .iter()
+        .filter_map(|field| object.get(field))
+        .map(|value| match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        })
+        .collect();
+    Some(values.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn json_lines_show_the_selected_fields_in_order() {
+        let line = r#"{"level":"info","msg":"listening","port":8080,"tags":["a"]}"#;
+        assert_eq!(
+            format_json_line(line, &fields(&["msg", "level", "port", "user", "tags"])),
+            Some(r#"listening info 8080 ["a"]"#.to_string())
+        );
+    }
+
+    #[test]
+    fn other_lines_are_left_raw() {
+        let fields = fields(&["msg"]);
+        assert_eq!(format_json_line("plain text", &fields), None);
+        assert_eq!(format_json_line("[1, 2]", &fields), None);
+        assert_eq!(format_json_line(r#"{"msg": "cut"#, &fields), None);
+    }
+}
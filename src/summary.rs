@@ -0,0 +1,111 @@
+use crate::process::ProcessStatus;
+use std::time::Duration;
+
+/// Formats a duration compactly, e.g. `850ms`, `9.1s`, `2m13s` or `1h02m03s`.
+pub fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs >= 3600 {
+        format!("{}h{:02}m{:02}s", secs / 3600, secs % 3600 / 60, secs % 60)
+    } else if secs >= 60 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else if secs >= 1 {
+        format!("{:.1}s", d.as_secs_f64())
+    } else {
+        format!("{}ms", d.as_millis())
+    }
+}
+
+/// Builds the report printed when the runner exits: one row per process with its
+/// final state, exit code, restart count, total lines, startup latency of the
+/// latest start and accumulated runtime.
+///
+/// # Arguments
+/// * `statuses` - Process names paired with their final status.
+/// * `session` - Wall-clock time the runner was up.
+pub fn format_summary(statuses: &[(String, ProcessStatus)], session: Duration) -> String {
+    let header = [
+        "NAME", "STATE", "EXIT", "RESTARTS", "LINES", "READY", "RUNTIME",
+    ];
+    let rows: Vec<[String; 7]> = statuses
+        .iter()
+        .map(|(name, status)| {
+            [
+                name.clone(),
+                status.state.to_string(),
+                status.exit.map(|e| e.to_string()).unwrap_or("-".into()),
+                status.restarts().to_string(),
+                status.lines.to_string(),
+                status
+                    .startup_latency
+                    .map(format_duration)
+                    .unwrap_or("-".into()),
+                format_duration(status.total_runtime()),
+            ]
+        })
+        .collect();
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.chars().count());
+        }
+    }
+    let format_row = |cells: &[String]| {
+        let line = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, w)| format!("{:<w$}", cell, w = w))
+            .collect::<Vec<_>>()
+            .join("  ");
+        format!("{}\n", line.trim_end())
+    };
+    let mut out = format!("Session finished after {}\n\n", format_duration(session));
+    out.push_str(&format_row(&header.map(String::from)));
+    for row in &rows {
+        out.push_str(&format_row(row));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::{ExitReason, ProcessState};
+
+    #[test]
+    fn durations_are_formatted_compactly() {
+        assert_eq!(format_duration(Duration::from_millis(850)), "850ms");
+        assert_eq!(format_duration(Duration::from_millis(9100)), "9.1s");
+        assert_eq!(format_duration(Duration::from_secs(133)), "2m13s");
+        assert_eq!(format_duration(Duration::from_secs(3723)), "1h02m03s");
+    }
+
+    #[test]
+    fn summary_has_a_row_per_process_in_aligned_columns() {
+        let api = ProcessStatus {
+            state: ProcessState::Exited,
+            exit: Some(ExitReason::Code(1)),
+            starts: 3,
+            lines: 1200,
+            runtime: Duration::from_secs(75),
+            startup_latency: Some(Duration::from_millis(420)),
+            ..Default::default()
+        };
+        let worker = ProcessStatus {
+            state: ProcessState::Stopped,
+            starts: 1,
+            lines: 5,
+            runtime: Duration::from_millis(2500),
+            ..Default::default()
+        };
+        let statuses = [("api".to_string(), api), ("worker".to_string(), worker)];
+        let summary = format_summary(&statuses, Duration::from_secs(90));
+        assert_eq!(
+            summary,
+            "Session finished after 1m30s\n\
+             \n\
+             NAME    STATE    EXIT  RESTARTS  LINES  READY  RUNTIME\n\
+             api     exited   1     2         1200   420ms  1m15s\n\
+             worker  stopped  -     0         5      -      2.5s\n"
+        );
+    }
+}
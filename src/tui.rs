@@ -1,4 +1,20 @@
-use crate::process::{OutputChannels, ProcessCommand};
+use crate::ansi::{self, StyledRange};
+use crate::clipboard;
+use crate::config::{
+    Config, DEFAULT_MAX_LINES, Highlight, Keybindings, OnReady, PaneLayout, Pattern, ProcessConfig,
+    TitleField, TitleFormat, export_config, load_config,
+};
+use crate::diff::changed_ranges;
+use crate::error::RunnerError;
+use crate::highlight::{Severity, detect_severity};
+use crate::history::History;
+use crate::process::{
+    ExitReason, OneOff, OutputChannels, OutputLine, ProcessCommand, ProcessHandle, ProcessManager,
+    ProcessState, ProcessStatus, Stream, spawn_detached, spawn_oneoff,
+};
+use crate::snapshot::{PaneDump, write_snapshot};
+use crate::structured::format_json_line;
+use crate::summary::format_duration;
 use crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture},
     execute,
@@ -6,63 +22,913 @@ use crossterm::{
 };
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Alignment, Constraint, Direction, Layout},
-    style::{Color, Style},
-    widgets::{Block, Borders, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState},
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Position, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{
+        Block, Borders, Clear, Gauge, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState,
+    },
 };
-use std::{io, time::Duration};
+use regex::{Regex, RegexBuilder};
+use std::{
+    io,
+    ops::Range,
+    time::{Duration, Instant, SystemTime},
+};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// What a pane column shows: every line (`None`) or only the lines of one stream.
+type Column = Option<Stream>;
+
+/// Time without error lines after which the next error counts as a new failure,
+/// for `jump_to_error`.
+const HEALTHY_PERIOD: Duration = Duration::from_secs(5);
+
+/// State of a single process window: its channels, output buffer and view settings.
+struct Pane {
+    name: String,
+    /// Channels and status of the process; `None` for the aggregated error pane.
+    process: Option<ProcessHandle>,
+    buffer: Vec<OutputLine>,
+    /// Approximate memory used by the buffer, in bytes.
+    bytes: usize,
+    /// Number of lines dropped from the front of the buffer, so line numbers keep counting.
+    evicted: usize,
+    /// Index of the first visible row of the merged view, or of the stdout column
+    /// when split, counted in rendered (wrapped) rows.
+    scroll_offset: usize,
+    /// Index of the first visible row of the stderr column when split.
+    stderr_scroll_offset: usize,
+    wrap: bool,
+    line_numbers: bool,
+    /// Show stdout and stderr side by side while this pane is focused.
+    split: bool,
+    /// Column scrolled by the arrow keys while split.
+    split_column: Stream,
+    progress_pattern: Option<Pattern>,
+    /// Last progress percentage parsed from the output, shown as a gauge.
+    progress: Option<u16>,
+    /// Ask before stopping the process interactively.
+    confirm_stop: bool,
+    /// Fields shown for JSON lines; raw lines are shown when empty.
+    json_fields: Vec<String>,
+    /// Scroll to the bottom as new lines arrive.
+    follow: bool,
+    /// Jump to the first error of a new failure, turning `follow` off.
+    jump_to_error: bool,
+    /// When the last error-level line was received.
+    last_error: Option<Instant>,
+    /// Lines matching this pattern are diffed against the previous matching one.
+    diff_pattern: Option<Pattern>,
+    /// Text of the last line that matched `diff_pattern`.
+    last_diffed: Option<String>,
+    /// Template of the title, replacing the name and state button.
+    title_format: Option<TitleFormat>,
+    /// Discard incoming output instead of adding it to the buffer.
+    muted: bool,
+    /// Hold incoming output aside instead of adding it to the buffer.
+    paused: bool,
+    /// Lines received while paused, added to the buffer on resuming.
+    held: Vec<OutputLine>,
+    /// Approximate memory used by the held lines, in bytes.
+    held_bytes: usize,
+    /// Width of the widest timestamp in the buffer, shown before the text.
+    timestamp_width: usize,
+    /// Show only the stderr lines when not split.
+    stderr_only: bool,
+    /// Number of lines kept in the buffer, the oldest being dropped; `None` keeps all.
+    max_lines: Option<usize>,
+    /// Number of starts of the process when a restart was asked for, and when.
+    restarting: Option<(u32, Instant)>,
+    /// The process takes lines typed in input mode.
+    stdin: bool,
+    /// The process says when it is ready, so the title shows while it isn't.
+    checks_ready: bool,
+    /// Color of the border while the pane isn't focused.
+    accent: Color,
+    /// Show the uptime and the restarts in the title.
+    show_uptime: bool,
+}
+
+impl Pane {
+    fn new(name: String, process: Option<ProcessHandle>) -> Self {
+        Pane {
+            name,
+            process,
+            buffer: Vec::new(),
+            bytes: 0,
+            evicted: 0,
+            scroll_offset: 0,
+            stderr_scroll_offset: 0,
+            wrap: false,
+            line_numbers: false,
+            split: false,
+            split_column: Stream::Stdout,
+            progress_pattern: None,
+            progress: None,
+            confirm_stop: false,
+            json_fields: Vec::new(),
+            follow: true,
+            jump_to_error: false,
+            last_error: None,
+            diff_pattern: None,
+            last_diffed: None,
+            title_format: None,
+            accent: Color::White,
+            show_uptime: false,
+            muted: false,
+            paused: false,
+            held: Vec::new(),
+            held_bytes: 0,
+            timestamp_width: 0,
+            stderr_only: false,
+            max_lines: None,
+            restarting: None,
+            stdin: false,
+            checks_ready: false,
+        }
+    }
+
+    /// Column scrolled and searched while focused: the active one when split,
+    /// else the merged view, or stderr when filtered to it.
+    fn active_column(&self) -> Column {
+        if self.split {
+            Some(self.split_column)
+        } else {
+            self.stderr_only.then_some(Stream::Stderr)
+        }
+    }
+
+    /// Whether the process runs or is on its way to, as its status tells,
+    /// so that a process that ended on its own shows as stopped.
+    fn is_active(&self) -> bool {
+        self.process
+            .as_ref()
+            .is_some_and(|handle| handle.status.lock().unwrap().is_active())
+    }
+
+    /// Starts the process if it is stopped, or stops it if it is running.
+    fn toggle(&mut self) {
+        if let Some(handle) = &self.process {
+            let cmd = if self.is_active() {
+                ProcessCommand::Stop
+            } else {
+                ProcessCommand::Start
+            };
+            let _ = handle.control.try_send(cmd);
+        }
+    }
+
+    /// Restarts the process, along with the processes that restart with it.
+    fn restart(&mut self) {
+        if let Some(handle) = &self.process {
+            self.restarting = Some((handle.status.lock().unwrap().starts, Instant::now()));
+            let _ = handle
+                .control
+                .try_send(ProcessCommand::Restart { cascade: true });
+        }
+    }
+
+    /// Whether toggling the process now would stop it and needs to be confirmed first.
+    fn stop_needs_confirmation(&self) -> bool {
+        self.confirm_stop && self.is_active()
+    }
+
+    /// Approximate memory used by the buffer and the held lines together.
+    fn memory(&self) -> usize {
+        self.bytes + self.held_bytes
+    }
+
+    /// Drops the first `count` held lines.
+    fn drop_held(&mut self, count: usize) {
+        self.held_bytes -= self
+            .held
+            .drain(..count)
+            .map(|line| line_size(&line))
+            .sum::<usize>();
+    }
+
+    /// Adds a line to the buffer, or puts it in place of the last line of its
+    /// stream if it replaces that one, and returns the index it is at.
+    fn push(&mut self, line: OutputLine) -> usize {
+        self.bytes += line_size(&line);
+        if let Some(timestamp) = &line.timestamp {
+            self.timestamp_width = self.timestamp_width.max(timestamp.width());
+        }
+        if line.replaces
+            && let Some(index) = self
+                .buffer
+                .iter()
+                .rposition(|l| l.stream == line.stream && !l.note)
+        {
+            self.bytes -= line_size(&self.buffer[index]);
+            self.buffer[index] = line;
+            return index;
+        }
+        self.buffer.push(line);
+        self.buffer.len() - 1
+    }
+
+    /// Empties the buffer and resets the scroll offsets and line numbering, and
+    /// forgets what was derived from the old output: the progress, the line
+    /// diffed against and the last error. The log file, if any, is kept as is.
+    fn clear(&mut self) {
+        self.buffer.clear();
+        self.bytes = 0;
+        self.evicted = 0;
+        self.scroll_offset = 0;
+        self.stderr_scroll_offset = 0;
+        self.timestamp_width = 0;
+        self.progress = None;
+        self.last_diffed = None;
+        self.last_error = None;
+        self.follow = true;
+    }
+
+    /// Records an error-level line received at `now` and tells whether it starts
+    /// a new failure to jump to: the pane follows the output and had no errors
+    /// for `HEALTHY_PERIOD`.
+    fn error_starts_failure(&mut self, now: Instant) -> bool {
+        let healthy = self
+            .last_error
+            .is_none_or(|t| now.saturating_duration_since(t) >= HEALTHY_PERIOD);
+        self.last_error = Some(now);
+        self.jump_to_error && self.follow && healthy
+    }
+
+    /// Lines shown in a column, paired with their index in the buffer. Notes are
+    /// shown in every column.
+    fn lines(&self, column: Column) -> Vec<(usize, &OutputLine)> {
+        self.buffer
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| column.is_none_or(|stream| line.stream == stream || line.note))
+            .collect()
+    }
+
+    fn scroll(&self, column: Column) -> usize {
+        match column {
+            Some(Stream::Stderr) => self.stderr_scroll_offset,
+            _ => self.scroll_offset,
+        }
+    }
+
+    fn scroll_mut(&mut self, column: Column) -> &mut usize {
+        match column {
+            Some(Stream::Stderr) => &mut self.stderr_scroll_offset,
+            _ => &mut self.scroll_offset,
+        }
+    }
+}
+
+/// What the text typed at the prompt is used for.
+enum PromptKind {
+    /// A shell command to run in a popup.
+    Command,
+    /// Text to look for in the selected pane, or a regular expression.
+    Search { regex: bool },
+    /// A note to insert into the selected pane.
+    Note,
+    /// A line for the standard input of the selected process; the prompt stays
+    /// open for the next one.
+    Input,
+}
+
+/// Text being typed in the bottom line.
+struct Prompt {
+    kind: PromptKind,
+    input: String,
+}
+
+impl Prompt {
+    fn label(&self) -> &'static str {
+        match self.kind {
+            PromptKind::Command => "Run: ",
+            PromptKind::Search { regex: false } => "Search (Tab: regex): ",
+            PromptKind::Search { regex: true } => "Search regex (Tab: text): ",
+            PromptKind::Note => "Note: ",
+            PromptKind::Input => "Input (Esc: leave): ",
+        }
+    }
+}
+
+/// What the selected pane is searched for, ignoring case.
+struct Search {
+    /// As typed, repeated in messages.
+    query: String,
+    regex: bool,
+    matcher: Regex,
+}
+
+impl Search {
+    /// Compiles the query, taken literally unless `regex` is set.
+    fn new(query: &str, regex: bool) -> Result<Self, regex::Error> {
+        let pattern = if regex {
+            query.to_string()
+        } else {
+            regex::escape(query)
+        };
+        let matcher = RegexBuilder::new(&pattern).case_insensitive(true).build()?;
+        Ok(Search {
+            query: query.to_string(),
+            regex,
+            matcher,
+        })
+    }
+
+    /// Byte ranges of the non-empty matches in a line.
+    fn ranges(&self, text: &str) -> Vec<Range<usize>> {
+        self.matcher
+            .find_iter(text)
+            .map(|m| m.range())
+            .filter(|range| !range.is_empty())
+            .collect()
+    }
+}
+
+/// An ad-hoc command shown over the panes, with its captured output.
+struct Popup {
+    command: String,
+    process: OneOff,
+    buffer: Vec<OutputLine>,
+    scroll_offset: usize,
+}
+
+impl Popup {
+    fn new(command: String) -> Self {
+        Popup {
+            process: spawn_oneoff(&command),
+            command,
+            buffer: Vec::new(),
+            scroll_offset: 0,
+        }
+    }
+}
+
+/// What happens when a confirmation is accepted.
+enum ConfirmAction {
+    /// Stop the process of the pane at this index.
+    Stop(usize),
+    /// Stop every running process.
+    StopAll,
+    /// Quit, stopping all the processes.
+    Quit,
+}
+
+/// Time after a digit within which the next one extends the pane number, so
+/// that `1` then `2` selects pane 12.
+const PANE_NUMBER_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How often the modification time of the configuration file is checked.
+const CONFIG_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Modification time of a file, if it can be read.
+fn modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// The configuration file as loaded, and as last seen on disk.
+struct ConfigInfo {
+    /// Modification time when the file was loaded, or last exported.
+    loaded: Option<SystemTime>,
+    /// Modification time at the last check.
+    current: Option<SystemTime>,
+    checked: Instant,
+}
+
+impl ConfigInfo {
+    fn new(path: &str) -> Self {
+        let loaded = modified(path);
+        ConfigInfo {
+            loaded,
+            current: loaded,
+            checked: Instant::now(),
+        }
+    }
+
+    /// Reads the modification time again, at most every `CONFIG_CHECK_INTERVAL`.
+    fn refresh(&mut self, path: &str) {
+        if self.checked.elapsed() >= CONFIG_CHECK_INTERVAL {
+            self.current = modified(path);
+            self.checked = Instant::now();
+        }
+    }
+
+    /// Whether the file changed on disk since it was loaded.
+    fn stale(&self) -> bool {
+        self.current != self.loaded
+    }
+}
+
+/// Longest wait for a process to stop, or to become ready, while restarting all.
+const RESTART_STEP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A restart of every process in dependency order, advanced as each step completes:
+/// the processes are stopped from the last in `order` back, then started from
+/// the first one, each waiting until the previous one is ready.
+struct RestartAll {
+    /// Process names, each after its dependencies.
+    order: Vec<String>,
+    /// Steps done, out of twice the number of processes.
+    step: usize,
+    /// When the command of the current step was sent, if it was, and how many
+    /// times the process had been launched then.
+    sent: Option<(Instant, u32)>,
+}
+
+/// A yes/no question shown over the panes, answered with `y` or `n`.
+struct Confirm {
+    question: String,
+    action: ConfirmAction,
+}
+
+/// Whole TUI state: the panes plus the currently selected window.
+struct App {
+    panes: Vec<Pane>,
+    selected_window: usize,
+    max_width: Option<u16>,
+    /// Limit on the bytes held by all pane buffers together.
+    buffer_budget: Option<usize>,
+    /// Configuration file the display order is exported to.
+    config_path: String,
+    /// Feedback shown in place of the help line until the next key press.
+    message: Option<String>,
+    prompt: Option<Prompt>,
+    popup: Option<Popup>,
+    confirm: Option<Confirm>,
+    /// The user closed the overlay shown when no process could be started.
+    failure_dismissed: bool,
+    /// Ask before quitting with `q`.
+    confirm_quit: bool,
+    /// Quit once every process has ended.
+    run_to_completion: bool,
+    keybindings: Keybindings,
+    /// Number of last lines copied with `Y`, or the whole buffer.
+    copy_last: Option<usize>,
+    /// Colors given to the output lines by their contents.
+    highlight: Option<Highlight>,
+    /// Number of the pane selected by the digits typed so far, and when the last one was.
+    pane_number: Option<(usize, Instant)>,
+    /// Show the latest line from any process in the top row.
+    show_ticker: bool,
+    /// Name of the process that wrote the latest line, and its text. The pane
+    /// is looked up by name, as panes move.
+    ticker: Option<(String, String)>,
+    /// Last search, repeated with `n` and `N` and highlighted in the selected pane.
+    search: Option<Search>,
+    search_history: History,
+    /// File the search history is saved to, if it is kept across runs.
+    search_history_path: Option<String>,
+    /// Show the configuration file and its modification time in the top row.
+    show_config: bool,
+    config_info: ConfigInfo,
+    /// Apply the configuration file again whenever it changes on disk.
+    watch_config: bool,
+    /// The configuration file is to be applied again, as asked for with `L`.
+    reload: bool,
+    /// Process names, each after its dependencies, for restarting all.
+    start_order: Vec<String>,
+    restart_all: Option<RestartAll>,
+    ready_gate: Option<ReadyGate>,
+    /// Panes drawn on screen; the others are on other pages.
+    visible: Range<usize>,
+    /// The selected pane fills the whole screen, hiding the others.
+    maximized: bool,
+    layout: PaneLayout,
+}
+
+/// Watches the processes with `ready_when` and takes the `on_ready` actions
+/// each time they all become ready.
+struct ReadyGate {
+    /// Names of the processes that announce readiness.
+    processes: Vec<String>,
+    actions: OnReady,
+    /// Whether the actions were taken since the stack last became ready.
+    fired: bool,
+    /// When the stack started coming up, at launch or when it last went down.
+    since: Instant,
+}
+
+impl App {
+    fn new(config: &Config, config_path: &str, channels: OutputChannels) -> Self {
+        let mut panes: Vec<Pane> = channels
+            .into_iter()
+            .zip(&config.processes)
+            .map(|(handle, proc)| process_pane(config, proc, handle))
+            .collect();
+        if config.error_pane {
+            panes.push(Pane {
+                wrap: config.wrap,
+                line_numbers: config.line_numbers,
+                max_lines: line_limit(config.max_lines),
+                ..Pane::new("Errors".to_string(), None)
+            });
+        }
+        App {
+            panes,
+            selected_window: 0,
+            max_width: config.max_width,
+            buffer_budget: config.buffer_budget.map(|size| size.0),
+            config_path: config_path.to_string(),
+            message: None,
+            prompt: None,
+            popup: None,
+            confirm: None,
+            failure_dismissed: false,
+            confirm_quit: config.confirm_quit.unwrap_or(true),
+            run_to_completion: config.run_to_completion,
+            keybindings: config.keybindings.clone(),
+            copy_last: config.copy_lines,
+            highlight: config.highlight.clone(),
+            pane_number: None,
+            show_ticker: config.ticker,
+            ticker: None,
+            search: None,
+            search_history: config
+                .search_history
+                .as_deref()
+                .map(History::load)
+                .unwrap_or_default(),
+            search_history_path: config.search_history.clone(),
+            start_order: config
+                .start_order()
+                .into_iter()
+                .map(|i| config.processes[i].name.clone())
+                .collect(),
+            restart_all: None,
+            show_config: config.show_config,
+            config_info: ConfigInfo::new(config_path),
+            watch_config: config.watch_config,
+            reload: false,
+            ready_gate: config.on_ready.clone().map(|actions| ReadyGate {
+                processes: ready_processes(config),
+                actions,
+                fired: false,
+                since: Instant::now(),
+            }),
+            visible: 0..0,
+            maximized: false,
+            layout: config.layout,
+        }
+    }
+
+    /// Starts the process of every pane that is stopped, but the disabled
+    /// ones, or stops every one that is running. Processes that exited or
+    /// failed to start count as stopped.
+    fn set_all_running(&mut self, running: bool) {
+        for pane in &mut self.panes {
+            let Some(handle) = &pane.process else {
+                continue;
+            };
+            let (active, disabled) = {
+                let status = handle.status.lock().unwrap();
+                (status.is_active(), status.disabled)
+            };
+            if active != running && !(running && disabled) {
+                pane.toggle();
+            }
+        }
+    }
+
+    /// Loads the configuration file again and applies it to the running
+    /// processes. Panes of new processes are added after the others and their
+    /// processes started, and the panes of the processes left out are removed;
+    /// the others keep their buffers. A file that fails to load changes nothing.
+    fn reload_config(&mut self, manager: &mut ProcessManager) {
+        // Whether it applies or not, this version of the file has been seen.
+        self.config_info = ConfigInfo::new(&self.config_path);
+        let config = match load_config(&self.config_path) {
+            Ok(config) => config,
+            Err(e) => {
+                self.message = Some(format!("Configuration not reloaded: {}", e));
+                return;
+            }
+        };
+        let selected = self.panes.get(self.selected_window).map(|p| p.name.clone());
+        let changes = manager.apply_config(&config);
+        self.panes.retain(|pane| {
+            pane.process
+                .as_ref()
+                .is_none_or(|handle| !changes.removed.contains(&handle.name))
+        });
+        let mut at = self
+            .panes
+            .iter()
+            .position(|pane| pane.process.is_none())
+            .unwrap_or(self.panes.len());
+        let added = changes.added.len();
+        for handle in changes.added {
+            let Some(proc) = config.processes.iter().find(|p| p.name == handle.name) else {
+                continue;
+            };
+            if proc.starts_automatically() {
+                let _ = handle.control.try_send(ProcessCommand::Start);
+            }
+            self.panes.insert(at, process_pane(&config, proc, handle));
+            at += 1;
+        }
+        self.selected_window = self
+            .panes
+            .iter()
+            .position(|pane| Some(&pane.name) == selected.as_ref())
+            .unwrap_or(self.selected_window.min(self.panes.len().saturating_sub(1)));
+        self.confirm = None;
+        self.ticker = None;
+        self.start_order = config
+            .start_order()
+            .into_iter()
+            .map(|i| config.processes[i].name.clone())
+            .collect();
+        if let Some(gate) = &mut self.ready_gate {
+            gate.processes = ready_processes(&config);
+        }
+        self.message = Some(format!(
+            "Configuration reloaded: {} added, {} removed, {} restarted",
+            added,
+            changes.removed.len(),
+            changes.changed.len()
+        ));
+    }
+
+    /// Inserts a timestamped note at the end of the selected pane's buffer, as
+    /// a marker in its timeline, and has the process log it with its output.
+    fn add_note(&mut self, layout: &[Rect], text: &str) {
+        let idx = self.selected_window;
+        let pane = &mut self.panes[idx];
+        let now = chrono::Local::now().format("%H:%M:%S");
+        let text = format!("── {} note: {} ──", now, text);
+        if let Some(handle) = &pane.process {
+            let _ = handle.control.try_send(ProcessCommand::Note(text.clone()));
+        }
+        pane.push(OutputLine {
+            note: true,
+            ..OutputLine::new(Stream::Stdout, text)
+        });
+        fit_scroll(pane, layout[idx], true, pane.follow);
+    }
+
+    /// Sends a line to the standard input of the selected process, and shows it
+    /// in its pane, dimmed, as it isn't echoed back.
+    fn send_input(&mut self, layout: &[Rect], line: &str) {
+        let idx = self.selected_window;
+        let pane = &mut self.panes[idx];
+        let Some(handle) = &pane.process else {
+            return;
+        };
+        let _ = handle
+            .control
+            .try_send(ProcessCommand::Input(format!("{}\n", line)));
+        pane.push(OutputLine {
+            styles: vec![StyledRange {
+                start: 0,
+                end: line.len(),
+                style: ansi::Style::default()
+                    .fg(ansi::Color::Indexed(6))
+                    .add_modifier(ansi::Modifier::DIM),
+            }],
+            ..OutputLine::new(Stream::Stdout, line)
+        });
+        fit_scroll(pane, layout[idx], true, pane.follow);
+    }
+
+    /// Looks for the last search in the selected pane, ignoring case, and
+    /// scrolls to the matching line. The search goes from the top visible line
+    /// to older output, or to newer output with `forward`, wrapping around.
+    /// The pane then stops following the output.
+    fn find_next(&mut self, layout: &[Rect], forward: bool) {
+        let Some(search) = &self.search else {
+            return;
+        };
+        let idx = self.selected_window;
+        let pane = &mut self.panes[idx];
+        let column = pane.active_column();
+        let rect = pane_columns(pane, layout[idx], true)
+            .into_iter()
+            .find(|(c, _)| *c == column)
+            .map_or(layout[idx], |(_, rect)| rect);
+        let (width, _) = text_area(pane, rect);
+        let lines = pane.lines(column);
+        // Position in `lines` of the line at the top of the view.
+        let mut rows = 0;
+        let top = lines
+            .iter()
+            .position(|(_, line)| {
+                rows += if pane.wrap {
+                    row_count(&line.text, width)
+                } else {
+                    1
+                };
+                rows > pane.scroll(column)
+            })
+            .unwrap_or(lines.len().saturating_sub(1));
+        let n = lines.len();
+        let found = (1..=n)
+            .map(|step| {
+                if forward {
+                    (top + step) % n
+                } else {
+                    (top + n - step) % n
+                }
+            })
+            .find(|&k| search.matcher.is_match(&lines[k].1.text))
+            .map(|k| lines[k].0);
+        self.message = Some(match found {
+            Some(index) => {
+                pane.follow = false;
+                scroll_to_line(pane, layout[idx], true, index);
+                format!(
+                    "Found '{}' at line {}",
+                    search.query,
+                    pane.evicted + index + 1
+                )
+            }
+            None => format!("'{}' not found in {}", search.query, pane.name),
+        });
+    }
+
+    /// Moves the selected process pane one place up (`-1`) or down (`1`) among
+    /// the process panes, keeping it selected.
+    fn move_selected(&mut self, delta: isize) {
+        let idx = self.selected_window;
+        let Some(target) = idx.checked_add_signed(delta) else {
+            return;
+        };
+        let movable = |i: usize| self.panes.get(i).is_some_and(|p| p.process.is_some());
+        if movable(idx) && movable(target) {
+            self.panes.swap(idx, target);
+            self.selected_window = target;
+        }
+    }
+
+    /// Whether every process has ended, or waits for dependencies that won't
+    /// be ready, and none is going to be restarted.
+    fn all_settled(&self) -> bool {
+        self.panes
+            .iter()
+            .filter_map(|p| p.process.as_ref())
+            .all(|h| h.status.lock().unwrap().is_settled())
+    }
+
+    /// Returns the name and error of every process when none of them could be
+    /// started in the first place, and `None` as soon as one has run.
+    fn startup_failures(&self) -> Option<Vec<(String, String)>> {
+        let statuses: Vec<(String, ProcessStatus)> = self
+            .panes
+            .iter()
+            .filter_map(|p| {
+                Some((
+                    p.name.clone(),
+                    p.process.as_ref()?.status.lock().unwrap().clone(),
+                ))
+            })
+            .collect();
+        all_failed(&statuses).then(|| {
+            statuses
+                .into_iter()
+                .filter(|(_, status)| !status.waits_for_user())
+                .map(|(name, status)| (name, status.spawn_error.unwrap_or_default()))
+                .collect()
+        })
+    }
+
+    /// Writes the buffers of all panes to a snapshot file and reports its path.
+    fn snapshot(&mut self) {
+        let dumps: Vec<PaneDump> = self
+            .panes
+            .iter()
+            .map(|pane| {
+                let status = pane
+                    .process
+                    .as_ref()
+                    .map(|h| h.status.lock().unwrap().clone());
+                PaneDump {
+                    name: &pane.name,
+                    command: status.as_ref().map(|status| status.command.clone()),
+                    status,
+                    lines: &pane.buffer,
+                }
+            })
+            .collect();
+        self.message = Some(match write_snapshot(&self.config_path, &dumps) {
+            Ok(path) => format!("Snapshot written to {}", path.display()),
+            Err(e) => format!("Snapshot failed: {}", e),
+        });
+    }
+
+    /// Puts lines of output of the selected pane on the system clipboard, one
+    /// per line, and reports how it went.
+    fn copy_lines(&mut self, lines: Vec<String>) {
+        let text: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+        let name = &self.panes[self.selected_window].name;
+        self.message = Some(match clipboard::copy(&text) {
+            Ok(()) => format!("Copied {} lines of {}", lines.len(), name),
+            Err(e) => format!("Copy failed: {}", e),
+        });
+    }
+
+    /// Writes the process panes' current order back to the configuration file.
+    fn export(&mut self) {
+        let order: Vec<&str> = self
+            .panes
+            .iter()
+            .filter(|p| p.process.is_some())
+            .map(|p| p.name.as_str())
+            .collect();
+        self.message = Some(match export_config(&self.config_path, &order) {
+            Ok(()) => {
+                self.config_info = ConfigInfo::new(&self.config_path);
+                format!("Saved process order to {}", self.config_path)
+            }
+            Err(e) => format!("Export failed: {}", e),
+        });
+    }
+}
+
+/// Whether every process started automatically failed its initial spawn,
+/// having never run.
+fn all_failed(statuses: &[(String, ProcessStatus)]) -> bool {
+    statuses.iter().any(|(_, s)| !s.waits_for_user())
+        && statuses.iter().all(|(_, s)| {
+            s.waits_for_user() || (s.state == ProcessState::Failed && s.starts == 0 && !s.retrying)
+        })
+}
 
 /// Runs the TUI event loop, rendering process windows and handling user input.
 /// Starts all processes, updates buffers with output, and manages scroll and process control.
 ///
 /// # Arguments
+/// * `config` - The loaded configuration, used for the per-pane view defaults.
+/// * `config_path` - Path of the configuration file, rewritten on export.
 /// * `channels` - The output and control channels for each process.
 ///
 /// # Returns
-/// * `Result<(), Box<dyn std::error::Error>>` - Ok on normal exit, Err on failure.
+/// * `Result<(), RunnerError>` - Ok on normal exit, Err on failure of the
+///   terminal, or `SpawnFailed` when no process could be started at all.
 pub async fn run_tui(
-    // config: crate::config::Config,
-    mut channels: OutputChannels,
-) -> Result<(), Box<dyn std::error::Error>> {
+    config: &Config,
+    config_path: &str,
+    channels: OutputChannels,
+    manager: &mut ProcessManager,
+) -> Result<(), RunnerError> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = ratatui::Terminal::new(backend)?;
 
-    let mut buffers: Vec<Vec<String>> = vec![Vec::new(); channels.len()];
-    let mut running: Vec<bool> = vec![true; channels.len()];
-    let mut scroll_offsets: Vec<u16> = vec![0; channels.len()];
-    let mut selected_window: usize = 0;
+    let mut app = App::new(config, config_path, channels);
 
-    // Start all processes
-    for (_, _, tx) in &channels {
-        let _ = tx.try_send(ProcessCommand::Start);
+    // Start all processes but those left for the user to start
+    for handle in app.panes.iter().filter_map(|p| p.process.as_ref()) {
+        if !handle.status.lock().unwrap().waits_for_user() {
+            let _ = handle.control.try_send(ProcessCommand::Start);
+        }
     }
 
+    let mut last_layout = None;
     loop {
-        let layout = get_layout(&mut terminal, channels.len());
-        update_buffers_and_scroll(&mut channels, &mut buffers, &layout, &mut scroll_offsets);
+        // Before the layout, which has to cover the panes the reload adds.
+        if app.show_config || app.watch_config {
+            app.config_info.refresh(&app.config_path);
+        }
+        if app.reload || (app.watch_config && app.config_info.stale()) {
+            app.reload = false;
+            app.reload_config(manager);
+        }
+        let area = content_area(terminal.get_frame().area(), app.max_width);
+        let per_page = if app.maximized {
+            1
+        } else {
+            page_size(area, app.panes.len(), app.layout)
+        };
+        let layout = get_layout(area, app.panes.len(), per_page, app.layout);
+        if last_layout != Some((per_page, app.layout)) {
+            // The panes changed size, on maximizing or restoring one or on a new layout.
+            for (i, (pane, rect)) in app.panes.iter_mut().zip(&layout).enumerate() {
+                fit_scroll(pane, *rect, i == app.selected_window, pane.follow);
+            }
+            last_layout = Some((per_page, app.layout));
+        }
+        app.visible = visible_panes(app.panes.len(), per_page, app.selected_window);
+        update_buffers_and_scroll(&mut app, &layout);
+        update_popup(&mut app, area);
+        update_restart_all(&mut app);
+        update_ready_gate(&mut app);
 
         terminal.draw(|f| {
-            draw_process_windows(
-                f,
-                &channels,
-                &buffers,
-                &running,
-                &scroll_offsets,
-                &selected_window,
-            );
-            draw_help_line(f, channels.len());
+            draw_process_windows(f, &app, &layout);
+            draw_ticker(f, &app, area);
+            draw_config_info(f, &app, area);
+            draw_popup(f, &app, area);
+            draw_confirm(f, &app, area);
+            draw_startup_failure(f, &app, area);
+            draw_help_line(f, &app);
         })?;
 
-        if handle_input_event(
-            &mut channels,
-            &mut running,
-            &mut scroll_offsets,
-            &mut selected_window,
-            &buffers,
-        )? {
+        if app.run_to_completion && app.all_settled() {
+            break;
+        }
+        if handle_input_event(&mut app, &layout, area)? {
             break;
         }
     }
@@ -73,191 +939,2412 @@ pub async fn run_tui(
         DisableMouseCapture
     )?;
     terminal.show_cursor()?;
+    if let Some(failures) = app.startup_failures() {
+        return Err(RunnerError::SpawnFailed(failures));
+    }
     Ok(())
 }
 
-/// Returns a vector of layout rectangles for each process window, splitting the terminal vertically.
-/// Each window gets an equal share of the available space.
+/// Returns the part of the terminal used for the panes: the full area, or a
+/// horizontally centered strip when it is wider than `max_width`.
+fn content_area(area: Rect, max_width: Option<u16>) -> Rect {
+    match max_width {
+        Some(max) if area.width > max => Rect {
+            x: area.x + (area.width - max) / 2,
+            width: max,
+            ..area
+        },
+        _ => area,
+    }
+}
+
+/// Returns a vector of layout rectangles for each process window, splitting the area
+/// as the layout says. Each window gets an equal share of the available space.
 ///
 /// # Arguments
-/// * `terminal` - The terminal instance to get the area from.
+/// * `area` - The area available for the panes.
 /// * `n` - The number of process windows to split the area into.
+/// * `per_page` - The number of windows sharing the area at once.
+/// * `layout` - How the windows of a page are arranged.
 ///
 /// # Returns
 /// * `Vec<ratatui::layout::Rect>` - The rectangles for each process window.
-fn get_layout(
-    terminal: &mut ratatui::Terminal<CrosstermBackend<std::io::Stdout>>,
-    n: usize,
-) -> Vec<ratatui::layout::Rect> {
-    let term_area = terminal.get_frame().area();
+fn get_layout(area: Rect, n: usize, per_page: usize, layout: PaneLayout) -> Vec<Rect> {
+    let inner = area.inner(Margin {
+        horizontal: 1,
+        vertical: 1,
+    });
+    (0..n)
+        .step_by(per_page)
+        .flat_map(|first| {
+            let count = per_page.min(n - first);
+            match layout {
+                PaneLayout::Vertical => split_evenly(inner, Direction::Vertical, count),
+                PaneLayout::Horizontal => split_evenly(inner, Direction::Horizontal, count),
+                PaneLayout::Grid => {
+                    let columns = grid_columns(count);
+                    let rows = split_evenly(inner, Direction::Vertical, count.div_ceil(columns));
+                    rows.into_iter()
+                        .enumerate()
+                        .flat_map(|(row, rect)| {
+                            let in_row = columns.min(count - row * columns);
+                            split_evenly(rect, Direction::Horizontal, in_row)
+                        })
+                        .collect()
+                }
+            }
+        })
+        .collect()
+}
+
+/// Splits an area into `count` equal parts in the given direction.
+fn split_evenly(area: Rect, direction: Direction, count: usize) -> Vec<Rect> {
     Layout::default()
-        .direction(Direction::Vertical)
-        .margin(1)
-        .constraints(vec![Constraint::Percentage(100 / n as u16); n])
-        .split(term_area)
+        .direction(direction)
+        .constraints(vec![Constraint::Percentage(100 / count as u16); count])
+        .split(area)
         .to_vec()
 }
 
-/// Updates the output buffers for each process by draining their channels.
-/// Also manages autoscroll: if new lines are added, scrolls to show the latest output.
+/// Number of columns of a grid of `n` panes: the square root of `n`, rounded up.
+fn grid_columns(n: usize) -> usize {
+    (1..=n).find(|c| c * c >= n).unwrap_or(1)
+}
+
+/// Lines scrolled by a step of the mouse wheel.
+const WHEEL_LINES: usize = 3;
+
+/// Smallest height of a pane, borders included, before the panes are paged.
+const MIN_PANE_HEIGHT: u16 = 6;
+
+/// Smallest width of a pane side by side with others, borders included.
+const MIN_PANE_WIDTH: u16 = 24;
+
+/// Number of panes shown at once: all of them if each gets at least
+/// `MIN_PANE_HEIGHT` rows and, beside others, `MIN_PANE_WIDTH` columns,
+/// otherwise as many as fit.
+fn page_size(area: Rect, n: usize, layout: PaneLayout) -> usize {
+    let rows = (area.height.saturating_sub(2) / MIN_PANE_HEIGHT).max(1) as usize;
+    let columns = (area.width.saturating_sub(2) / MIN_PANE_WIDTH).max(1) as usize;
+    let fitting = match layout {
+        PaneLayout::Vertical => rows,
+        PaneLayout::Horizontal => columns,
+        PaneLayout::Grid => rows * grid_columns(n).min(columns),
+    };
+    n.min(fitting).max(1)
+}
+
+/// Indices of the panes on the page of the selected one. Every pane has a
+/// place in the layout, on its own page, so off-page panes keep scrolling right.
+fn visible_panes(n: usize, per_page: usize, selected: usize) -> Range<usize> {
+    let first = selected / per_page * per_page;
+    first..(first + per_page).min(n)
+}
+
+/// Returns the columns a pane displays in `area`: the merged view, or only
+/// stderr when filtered to it, or an stdout and an stderr column side by side
+/// when the pane is split and focused.
+fn pane_columns(pane: &Pane, area: Rect, focused: bool) -> Vec<(Column, Rect)> {
+    if pane.split && focused {
+        let halves = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(area);
+        vec![
+            (Some(Stream::Stdout), halves[0]),
+            (Some(Stream::Stderr), halves[1]),
+        ]
+    } else {
+        vec![(pane.stderr_only.then_some(Stream::Stderr), area)]
+    }
+}
+
+/// Width of the gutter of a pane, with the line numbers and the timestamps,
+/// each followed by a separating space. Zero when neither is shown.
+fn gutter_width(pane: &Pane) -> usize {
+    numbers_width(pane) + timestamps_width(pane)
+}
+
+/// Width of the line numbers in the gutter, including the separating space.
+fn numbers_width(pane: &Pane) -> usize {
+    if pane.line_numbers {
+        (pane.evicted + pane.buffer.len()).max(1).to_string().len() + 1
+    } else {
+        0
+    }
+}
+
+/// Width of the timestamps in the gutter, including the separating space.
+fn timestamps_width(pane: &Pane) -> usize {
+    if pane.timestamp_width > 0 {
+        pane.timestamp_width + 1
+    } else {
+        0
+    }
+}
+
+/// Returns the width available for text and the number of visible rows inside a pane column.
+/// The bottom row is left out when a progress gauge is shown.
+fn text_area(pane: &Pane, area: Rect) -> (usize, usize) {
+    let inner_width = area.width.saturating_sub(2) as usize;
+    let inner_height = area.height.saturating_sub(2) as usize;
+    let gauge_height = usize::from(pane.progress.is_some());
+    (
+        inner_width.saturating_sub(gutter_width(pane)),
+        inner_height.saturating_sub(gauge_height),
+    )
+}
+
+/// Extracts a progress percentage from a line using the pane's progress pattern.
 ///
-/// # Arguments
-/// * `channels` - Mutable reference to the process output channels.
-/// * `buffers` - Mutable reference to the output buffers for each process.
-/// * `layout` - The layout rectangles for each process window.
-/// * `scroll_offsets` - Mutable reference to the scroll offsets for each process window.
-fn update_buffers_and_scroll(
-    channels: &mut OutputChannels,
-    buffers: &mut Vec<Vec<String>>,
-    layout: &[ratatui::layout::Rect],
-    scroll_offsets: &mut Vec<u16>,
-) {
-    for (i, (_, rx, _)) in channels.iter_mut().enumerate() {
-        while let Ok(line) = rx.try_recv() {
-            buffers[i].push(line);
-            let visible_height = layout
-                .get(i)
-                .map(|a| a.height.saturating_sub(2))
-                .unwrap_or(0);
-            let buffer_len = buffers[i].len() as u16;
-            if buffer_len > visible_height && visible_height > 0 {
-                scroll_offsets[i] = buffer_len - visible_height;
-            }
+/// With one capture group, its value is taken as a percentage. With two, they are
+/// read as a current step and a total, e.g. `3/10`. The result is clamped to 0–100.
+fn parse_progress(pattern: &Pattern, text: &str) -> Option<u16> {
+    let captures = pattern.captures(text)?;
+    let value = |i: usize| captures.get(i)?.as_str().trim().parse::<f64>().ok();
+    let percent = match (value(1)?, value(2)) {
+        (current, Some(total)) if total > 0.0 => current / total * 100.0,
+        (_, Some(_)) => return None,
+        (percent, None) => percent,
+    };
+    Some(percent.clamp(0.0, 100.0).round() as u16)
+}
+
+/// Number of rows a line of text occupies once wrapped at `width` columns.
+fn row_count(text: &str, width: usize) -> usize {
+    if width == 0 {
+        return 1;
+    }
+    let mut rows = 1;
+    let mut used = 0;
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w > width && used > 0 {
+            rows += 1;
+            used = 0;
         }
+        used += w;
     }
+    rows
 }
 
-/// Draws each process window, including its output, title, and a vertical scrollbar.
-/// Each window shows the process name, a start/stop button, and the current output buffer.
-///
-/// # Arguments
-/// * `f` - The ratatui frame to render into.
-/// * `channels` - The process channels (names and control).
-/// * `buffers` - The output buffers for each process.
-/// * `running` - The running/stopped state for each process.
-/// * `scroll_offsets` - The scroll offset for each process window.
-fn draw_process_windows<'a>(
-    f: &mut ratatui::Frame<'a>,
-    channels: &OutputChannels,
-    buffers: &Vec<Vec<String>>,
-    running: &Vec<bool>,
-    scroll_offsets: &Vec<u16>,
-    selected_window: &usize,
-) {
-    let layout = Layout::default()
-        .direction(Direction::Vertical)
-        .margin(1)
-        .constraints(vec![
-            Constraint::Percentage(100 / channels.len() as u16);
-            channels.len()
-        ])
-        .split(f.area());
-
-    for (i, area) in layout.iter().enumerate() {
-        let name = &channels[i].0;
-        let button = if running[i] { "[Started]" } else { "[Stoped]" };
-        let title = format!("{}. {} {}", i + 1, name, button);
-        let text = buffers[i].join("\n");
-        let border_color = if i == *selected_window {
-            Color::Yellow
-        } else {
-            Color::White
-        };
-        let para = Paragraph::new(text)
-            .block(
-                Block::default()
-                    .title(title.as_str())
-                    .borders(Borders::ALL)
-                    .border_style(Style::default().fg(border_color)),
-            )
-            .style(Style::default().fg(Color::White))
-            .scroll((scroll_offsets[i], 0));
-        f.render_widget(para, *area);
+/// Splits a line of text into rows of at most `width` columns.
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    if width == 0 {
+        return vec![text.to_string()];
+    }
+    let mut rows = Vec::new();
+    let mut row = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w > width && used > 0 {
+            rows.push(std::mem::take(&mut row));
+            used = 0;
+        }
+        row.push(c);
+        used += w;
+    }
+    rows.push(row);
+    rows
+}
 
-        let content_height = buffers[i].len() as u16;
-        let mut scrollbar_state = ScrollbarState::default()
-            .content_length(content_height as usize)
-            .position(scroll_offsets[i] as usize);
-        let scrollbar = Scrollbar::default().orientation(ScrollbarOrientation::VerticalRight);
-        f.render_stateful_widget(scrollbar, *area, &mut scrollbar_state);
+/// Total number of rendered rows for a pane column, honoring the pane's own wrap setting.
+fn total_rows(pane: &Pane, column: Column, width: usize) -> usize {
+    let lines = pane.lines(column);
+    if pane.wrap {
+        lines.iter().map(|(_, l)| row_count(&l.text, width)).sum()
+    } else {
+        lines.len()
     }
 }
 
-/// Draws a help line at the bottom of the screen with key bindings for the user.
-///
-/// # Arguments
-/// * `f` - The ratatui frame to render into.
-fn draw_help_line(f: &mut ratatui::Frame, num_windows: usize) {
-    let help = format!(
-        "(q: quit, 1-{}: select process, <Space> toggle process, ↑/↓: scroll)",
-        num_windows
-    );
-    let rect = f.area();
-    let help_area = ratatui::layout::Rect {
-        x: rect.x,
-        y: rect.y + rect.height.saturating_sub(1),
-        width: rect.width,
-        height: 1,
+/// Largest valid scroll offset for a pane column rendered in `area`.
+fn max_scroll(pane: &Pane, column: Column, area: Rect) -> usize {
+    let (width, height) = text_area(pane, area);
+    total_rows(pane, column, width).saturating_sub(height)
+}
+
+/// Clamps the scroll offsets of every displayed column to their valid range,
+/// or moves them to the bottom when `to_bottom` is set.
+fn fit_scroll(pane: &mut Pane, area: Rect, focused: bool, to_bottom: bool) {
+    for (column, rect) in pane_columns(pane, area, focused) {
+        let max = max_scroll(pane, column, rect);
+        let offset = pane.scroll_mut(column);
+        *offset = if to_bottom { max } else { (*offset).min(max) };
+    }
+}
+
+/// Scrolls a column of a pane to `offset`, clamped to its range. Scrolling up
+/// locks the pane, and reaching the bottom follows the output again.
+fn set_scroll(pane: &mut Pane, column: Column, rect: Rect, offset: usize) {
+    let max = max_scroll(pane, column, rect);
+    *pane.scroll_mut(column) = offset.min(max);
+    pane.follow = offset >= max;
+}
+
+/// Index in the buffer of the line shown at the top of a pane column.
+fn top_line(pane: &Pane, column: Column, rect: Rect) -> Option<usize> {
+    let (width, _) = text_area(pane, rect);
+    let offset = pane.scroll(column);
+    let mut rows = 0;
+    pane.lines(column).into_iter().find_map(|(i, line)| {
+        rows += if pane.wrap {
+            row_count(&line.text, width)
+        } else {
+            1
+        };
+        (rows > offset).then_some(i)
+    })
+}
+
+/// Buffer lines shown, even partly, in a pane column.
+fn shown_lines(pane: &Pane, column: Column, rect: Rect) -> Vec<&OutputLine> {
+    let (width, height) = text_area(pane, rect);
+    let Some(top) = top_line(pane, column, rect) else {
+        return Vec::new();
     };
-    f.render_widget(
-        Paragraph::new(help)
-            .style(Style::default().fg(Color::Yellow))
-            .alignment(Alignment::Center),
-        help_area,
-    );
+    let mut rows = 0;
+    pane.lines(column)
+        .into_iter()
+        .skip_while(|(i, _)| *i < top)
+        .take_while(|(_, line)| {
+            let shown = rows < height;
+            rows += if pane.wrap {
+                row_count(&line.text, width)
+            } else {
+                1
+            };
+            shown
+        })
+        .map(|(_, line)| line)
+        .collect()
 }
 
-/// Handles user input events for process control and scrolling.
-/// Returns Ok(true) if the user requested to quit, otherwise Ok(false).
+/// Number of buffer lines of a pane column that start below what is shown.
+fn lines_below(pane: &Pane, column: Column, rect: Rect) -> usize {
+    let (width, height) = text_area(pane, rect);
+    let bottom = pane.scroll(column) + height;
+    let mut rows = 0;
+    pane.lines(column)
+        .into_iter()
+        .filter(|(_, line)| {
+            let below = rows >= bottom;
+            rows += if pane.wrap {
+                row_count(&line.text, width)
+            } else {
+                1
+            };
+            below
+        })
+        .count()
+}
+
+/// Label at the bottom of a pane column scrolled up from the end of its output.
+fn new_lines_label(count: usize) -> String {
+    format!("▼ {} new line{}", count, if count == 1 { "" } else { "s" })
+}
+
+/// Returns the column of a pane whose new lines label, in its bottom border, a
+/// click at (`x`, `y`) hits. The label is right-aligned against the corner.
+fn hits_new_lines(
+    pane: &Pane,
+    area: Rect,
+    focused: bool,
+    x: u16,
+    y: u16,
+) -> Option<(Column, Rect)> {
+    pane_columns(pane, area, focused)
+        .into_iter()
+        .find(|(column, rect)| {
+            let below = lines_below(pane, *column, *rect);
+            if below == 0 || rect.height == 0 || y != rect.y + rect.height - 1 {
+                return false;
+            }
+            let end = (rect.x + rect.width).saturating_sub(1) as usize;
+            let start = end
+                .saturating_sub(new_lines_label(below).width())
+                .max(rect.x as usize + 1);
+            (start..end).contains(&(x as usize))
+        })
+}
+
+/// Scrolls every displayed column so that the buffer line at `index` is at the
+/// top, or as close to it as the scroll range allows.
+fn scroll_to_line(pane: &mut Pane, area: Rect, focused: bool, index: usize) {
+    for (column, rect) in pane_columns(pane, area, focused) {
+        let (width, _) = text_area(pane, rect);
+        let rows: usize = pane
+            .lines(column)
+            .iter()
+            .take_while(|(i, _)| *i < index)
+            .map(|(_, line)| {
+                if pane.wrap {
+                    row_count(&line.text, width)
+                } else {
+                    1
+                }
+            })
+            .sum();
+        let max = max_scroll(pane, column, rect);
+        *pane.scroll_mut(column) = rows.min(max);
+    }
+}
+
+/// Updates the output buffers for each process by draining their channels.
+/// Error-level lines are also copied, timestamped and tagged with the process name,
+/// into the aggregated error pane if there is one.
+/// Also manages autoscroll: if new lines are added, scrolls to show the latest output,
+/// unless the pane jumped to the first error of a new failure and stopped following.
 ///
 /// # Arguments
-/// * `channels` - Mutable reference to the process channels for sending control commands.
-/// * `running` - Mutable reference to the running/stopped state for each process.
-/// * `scroll_offsets` - Mutable reference to the scroll offsets for each process window.
-/// * `buffers` - Reference to the output buffers for each process.
-///
-/// # Returns
-/// * `Result<bool, Box<dyn std::error::Error>>` - Ok(true) if quit, Ok(false) otherwise.
-fn handle_input_event(
-    channels: &mut OutputChannels,
-    running: &mut Vec<bool>,
-    scroll_offsets: &mut Vec<u16>,
-    selected_window: &mut usize,
-    buffers: &Vec<Vec<String>>,
-) -> Result<bool, Box<dyn std::error::Error>> {
-    use crossterm::event::{self, Event, KeyCode};
-    if event::poll(Duration::from_millis(100))? {
-        if let Event::Key(key) = event::read()? {
-            match key.code {
-                KeyCode::Char('q') => return Ok(true),
-                KeyCode::Char(c) if c >= '1' && (c as usize - '1' as usize) < channels.len() => {
-                    let idx = c as usize - '1' as usize;
-                    *selected_window = idx;
-                }
-                KeyCode::Char(' ') => {
-                    let idx = *selected_window;
-                    running[idx] = !running[idx];
-                    let (_, _, tx) = &channels[idx];
-                    let cmd = if running[idx] {
-                        ProcessCommand::Start
-                    } else {
-                        ProcessCommand::Stop
-                    };
-                    let _ = tx.try_send(cmd);
-                }
-                KeyCode::Up => {
-                    if scroll_offsets[*selected_window] > 0 {
-                        scroll_offsets[*selected_window] -= 1;
-                    }
+/// * `app` - Mutable reference to the TUI state.
+/// * `layout` - The layout rectangles for each process window.
+fn update_buffers_and_scroll(app: &mut App, layout: &[Rect]) {
+    let collect_errors = app.panes.iter().any(|p| p.process.is_none());
+    let mut errors = Vec::new();
+    for (i, (pane, area)) in app.panes.iter_mut().zip(layout).enumerate() {
+        if pane.process.is_none() {
+            continue;
+        }
+        let incoming = take_incoming(pane);
+        let received = !incoming.is_empty();
+        if app.show_ticker
+            && let Some(line) = incoming.last()
+        {
+            app.ticker = Some((pane.name.clone(), line.text.clone()));
+        }
+        let mut first_error = None;
+        for mut line in incoming {
+            if !pane.json_fields.is_empty()
+                && let Some(text) = format_json_line(&line.text, &pane.json_fields)
+            {
+                line.text = text;
+                line.links.clear();
+                line.styles.clear();
+            }
+            if let Some(pattern) = &pane.diff_pattern
+                && pattern.is_match(&line.text)
+            {
+                if let Some(previous) = &pane.last_diffed {
+                    line.changes = changed_ranges(previous, &line.text).unwrap_or_default();
                 }
-                KeyCode::Down => {
-                    if scroll_offsets[*selected_window] < buffers[*selected_window].len() as u16 {
-                        scroll_offsets[*selected_window] += 1;
-                    }
+                pane.last_diffed = Some(line.text.clone());
+            }
+            if let Some(pattern) = &pane.progress_pattern
+                && let Some(progress) = parse_progress(pattern, &line.text)
+            {
+                pane.progress = Some(progress);
+            }
+            let mut starts_failure = false;
+            if detect_severity(&line.text) == Some(Severity::Error) {
+                starts_failure = pane.error_starts_failure(Instant::now());
+                if collect_errors {
+                    let now = chrono::Local::now().format("%H:%M:%S");
+                    errors.push(OutputLine::new(
+                        line.stream,
+                        format!("{} [{}] {}", now, pane.name, line.text),
+                    ));
                 }
-                _ => {}
+            }
+            let index = pane.push(line);
+            if starts_failure && first_error.is_none() {
+                first_error = Some(index);
             }
         }
+        let focused = i == app.selected_window;
+        if let Some(max) = pane.max_lines
+            && pane.buffer.len() > max
+        {
+            let excess = pane.buffer.len() - max;
+            evict_lines(pane, excess, *area, focused);
+            first_error = first_error.map(|index| index.saturating_sub(excess));
+        }
+        if let Some(index) = first_error {
+            pane.follow = false;
+            scroll_to_line(pane, *area, focused, index);
+        } else if received {
+            fit_scroll(pane, *area, focused, pane.follow);
+        }
+    }
+    if !errors.is_empty()
+        && let Some((i, (pane, area))) = app
+            .panes
+            .iter_mut()
+            .zip(layout)
+            .enumerate()
+            .find(|(_, (p, _))| p.process.is_none())
+    {
+        for line in errors {
+            pane.push(line);
+        }
+        let focused = i == app.selected_window;
+        if let Some(max) = pane.max_lines
+            && pane.buffer.len() > max
+        {
+            evict_lines(pane, pane.buffer.len() - max, *area, focused);
+        }
+        fit_scroll(pane, *area, focused, true);
+    }
+    if let Some(budget) = app.buffer_budget {
+        enforce_budget(app, budget, layout);
+    }
+}
+
+/// Drains the output channel of a pane and returns the lines to add to its
+/// buffer: none while it is muted, as they are discarded, or paused, as they
+/// are held aside, up to `max_lines`. On resuming, the held lines come first.
+fn take_incoming(pane: &mut Pane) -> Vec<OutputLine> {
+    let Some(handle) = &mut pane.process else {
+        return Vec::new();
+    };
+    let mut incoming = Vec::new();
+    while let Ok(line) = handle.output.try_recv() {
+        incoming.push(line);
+    }
+    if pane.muted {
+        incoming.clear();
+    }
+    if pane.paused {
+        pane.held_bytes += incoming.iter().map(line_size).sum::<usize>();
+        pane.held.append(&mut incoming);
+        if let Some(max) = pane.max_lines {
+            pane.drop_held(pane.held.len().saturating_sub(max));
+        }
+        return Vec::new();
+    }
+    if !pane.held.is_empty() {
+        incoming.splice(0..0, std::mem::take(&mut pane.held));
+        pane.held_bytes = 0;
+    }
+    incoming
+}
+
+/// Turns a configured `max_lines` into the limit of a pane: the default when
+/// not set, and no limit for 0.
+fn line_limit(max_lines: Option<usize>) -> Option<usize> {
+    Some(max_lines.unwrap_or(DEFAULT_MAX_LINES)).filter(|&max| max > 0)
+}
+
+/// Approximate memory taken by a buffered line.
+fn line_size(line: &OutputLine) -> usize {
+    std::mem::size_of::<OutputLine>() + line.text.len()
+}
+
+/// Keeps the buffers and held lines of all panes within `budget` bytes
+/// together by dropping the oldest lines of whichever pane uses the most
+/// memory: first from its buffer, then from the lines held while paused.
+fn enforce_budget(app: &mut App, budget: usize, layout: &[Rect]) {
+    let mut total: usize = app.panes.iter().map(Pane::memory).sum();
+    while total > budget {
+        let Some(largest) = (0..app.panes.len()).max_by_key(|&i| app.panes[i].memory()) else {
+            break;
+        };
+        let runner_up = (0..app.panes.len())
+            .filter(|&i| i != largest)
+            .map(|i| app.panes[i].memory())
+            .max()
+            .unwrap_or(0);
+        // Trim the largest pane until the total fits or it is no longer the largest.
+        let pane = &mut app.panes[largest];
+        let lines = if pane.buffer.is_empty() {
+            &pane.held
+        } else {
+            &pane.buffer
+        };
+        let (mut count, mut freed) = (0, 0);
+        while count < lines.len() && total - freed > budget && pane.memory() - freed >= runner_up {
+            freed += line_size(&lines[count]);
+            count += 1;
+        }
+        if count == 0 {
+            break;
+        }
+        if pane.buffer.is_empty() {
+            pane.drop_held(count);
+        } else {
+            evict_lines(pane, count, layout[largest], largest == app.selected_window);
+        }
+        total -= freed;
+    }
+}
+
+/// Drops the first `count` lines of a pane's buffer, shifting its scroll offsets
+/// so the lines in view stay in place.
+fn evict_lines(pane: &mut Pane, count: usize, area: Rect, focused: bool) {
+    for (column, rect) in pane_columns(pane, area, focused) {
+        let (width, _) = text_area(pane, rect);
+        let rows: usize = pane.buffer[..count]
+            .iter()
+            .filter(|line| column.is_none_or(|stream| line.stream == stream || line.note))
+            .map(|line| {
+                if pane.wrap {
+                    row_count(&line.text, width)
+                } else {
+                    1
+                }
+            })
+            .sum();
+        let offset = pane.scroll_mut(column);
+        *offset = offset.saturating_sub(rows);
+    }
+    pane.bytes -= pane
+        .buffer
+        .drain(..count)
+        .map(|line| line_size(&line))
+        .sum::<usize>();
+    pane.evicted += count;
+    fit_scroll(pane, area, focused, false);
+}
+
+/// Returns a rectangle of the given size centered in `area`, shrunk to fit it.
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+/// Returns the area of the command popup, centered over the panes.
+fn popup_area(area: Rect) -> Rect {
+    centered(area, area.width * 4 / 5, area.height * 4 / 5)
+}
+
+/// Number of output rows visible in the command popup.
+fn popup_height(area: Rect) -> usize {
+    popup_area(area).height.saturating_sub(2) as usize
+}
+
+/// Takes the `on_ready` actions when every process with `ready_when` has become
+/// ready. They are taken again if one of them stops being ready and the stack
+/// comes back up, e.g. after a restart.
+fn update_ready_gate(app: &mut App) {
+    let Some(gate) = &mut app.ready_gate else {
+        return;
+    };
+    let ready = gate.processes.iter().all(|name| {
+        app.panes
+            .iter()
+            .find(|p| p.name == *name)
+            .and_then(|p| p.process.as_ref())
+            .is_some_and(|handle| handle.status.lock().unwrap().is_ready())
+    });
+    if !ready {
+        if gate.fired {
+            gate.fired = false;
+            gate.since = Instant::now();
+        }
+        return;
+    }
+    if gate.fired {
+        return;
+    }
+    gate.fired = true;
+    let actions = &gate.actions;
+    if let Some(message) = &actions.message {
+        app.message = Some(format!(
+            "{} (after {})",
+            message,
+            format_duration(gate.since.elapsed())
+        ));
+    }
+    if let Some(command) = &actions.command
+        && let Err(e) = spawn_detached(command)
+    {
+        app.message = Some(format!("Failed to run on_ready command: {}", e));
+    }
+    if actions.beep {
+        use std::io::Write;
+        let mut stdout = io::stdout();
+        let _ = stdout.write_all(b"\x07");
+        let _ = stdout.flush();
+    }
+}
+
+/// Advances a restart of all processes: sends the command of the current
+/// step, then moves on once the process has stopped, or is ready or gave up when
+/// starting, or the step timed out. Progress is shown in the status bar.
+fn update_restart_all(app: &mut App) {
+    let App {
+        panes,
+        restart_all,
+        message,
+        ..
+    } = app;
+    let Some(restart) = restart_all else {
+        return;
+    };
+    let n = restart.order.len();
+    if restart.step >= 2 * n {
+        *message = Some("Restarted all processes".to_string());
+        *restart_all = None;
+        return;
+    }
+    let stopping = restart.step < n;
+    let name = if stopping {
+        &restart.order[n - 1 - restart.step]
+    } else {
+        &restart.order[restart.step - n]
+    };
+    *message = Some(format!(
+        "Restarting all ({}/{}): {} {}",
+        restart.step + 1,
+        2 * n,
+        if stopping { "stopping" } else { "starting" },
+        name
+    ));
+    let Some(pane) = panes.iter_mut().find(|p| p.name == *name) else {
+        restart.step += 1;
+        return;
+    };
+    // Processes left for the user to start that were never started are left alone.
+    let Some(handle) = pane
+        .process
+        .as_ref()
+        .filter(|handle| !handle.status.lock().unwrap().waits_for_user())
+    else {
+        restart.step += 1;
+        return;
+    };
+    match restart.sent {
+        None => {
+            let cmd = if stopping {
+                ProcessCommand::Stop
+            } else {
+                ProcessCommand::Start
+            };
+            let starts = handle.status.lock().unwrap().starts;
+            let _ = handle.control.try_send(cmd);
+            restart.sent = Some((Instant::now(), starts));
+        }
+        Some((sent, starts)) => {
+            let status = handle.status.lock().unwrap();
+            let done = if stopping {
+                status.state != ProcessState::Running
+            } else {
+                // Only the run launched by this step counts.
+                status.starts > starts
+                    && (status.is_ready()
+                        || (status.state != ProcessState::Running && !status.retrying))
+            };
+            if done || sent.elapsed() >= RESTART_STEP_TIMEOUT {
+                restart.step += 1;
+                restart.sent = None;
+            }
+        }
+    }
+}
+
+/// Drains the output of the popup command, if any, following it to the bottom.
+fn update_popup(app: &mut App, area: Rect) {
+    let Some(popup) = &mut app.popup else {
+        return;
+    };
+    let mut received = false;
+    while let Ok(line) = popup.process.output.try_recv() {
+        popup.buffer.push(line);
+        received = true;
+    }
+    if received {
+        popup.scroll_offset = popup.buffer.len().saturating_sub(popup_height(area));
+    }
+}
+
+/// Color of a line with the given log level.
+fn severity_style(severity: Option<Severity>) -> Style {
+    match severity {
+        Some(Severity::Error) => Style::default().fg(Color::Red),
+        Some(Severity::Warn) => Style::default().fg(Color::Yellow),
+        Some(Severity::Debug) => Style::default().fg(Color::DarkGray),
+        Some(Severity::Info) | None => Style::default(),
+    }
+}
+
+/// Splits a row of a line into spans styled by layers of byte ranges of the
+/// line, given the byte offset of the row in it. Each layer covering a part is
+/// patched over the base style, in order, so later layers win.
+fn styled_spans(
+    piece: &str,
+    offset: usize,
+    base: Style,
+    layers: &[(Range<usize>, Style)],
+) -> Vec<Span<'static>> {
+    let end = offset + piece.len();
+    let mut cuts: Vec<usize> = layers
+        .iter()
+        .flat_map(|(range, _)| [range.start, range.end])
+        .filter(|&cut| cut > offset && cut < end)
+        .collect();
+    cuts.push(end);
+    cuts.sort_unstable();
+    cuts.dedup();
+    let mut at = offset;
+    cuts.into_iter()
+        .map(|cut| {
+            let style = layers
+                .iter()
+                .filter(|(range, _)| range.contains(&at))
+                .fold(base, |style, (_, layer)| style.patch(*layer));
+            let span = Span::styled(piece[at - offset..cut - offset].to_string(), style);
+            at = cut;
+            span
+        })
+        .collect()
+}
+
+/// A hyperlink on screen: the row it is on, its columns in the text area, and its target.
+struct LinkRegion {
+    row: usize,
+    columns: std::ops::Range<usize>,
+    url: String,
+}
+
+/// Finds the columns covered by the links of a line within one of its rows,
+/// given the byte offset of the row in the line and the width of the text area.
+fn link_regions(
+    line: &OutputLine,
+    piece: &str,
+    offset: usize,
+    row: usize,
+    width: usize,
+) -> Vec<LinkRegion> {
+    let column_at = |end: usize| {
+        let end = end.clamp(offset, offset + piece.len()) - offset;
+        piece[..end]
+            .chars()
+            .map(|c| c.width().unwrap_or(0))
+            .sum::<usize>()
+            .min(width)
+    };
+    line.links
+        .iter()
+        .filter(|link| link.start < offset + piece.len() && link.end > offset)
+        .map(|link| LinkRegion {
+            row,
+            columns: column_at(link.start)..column_at(link.end),
+            url: link.url.clone(),
+        })
+        .filter(|region| !region.columns.is_empty())
+        .collect()
+}
+
+/// Turns the cells under each link into an OSC 8 hyperlink, so terminals that
+/// support it make the text clickable. The escape sequences are attached to cells
+/// in pairs and the cell after each pair is skipped, which keeps the buffer's
+/// width accounting intact.
+fn mark_hyperlinks(
+    buffer: &mut ratatui::buffer::Buffer,
+    origin: (u16, u16),
+    regions: &[LinkRegion],
+) {
+    for region in regions {
+        let y = origin.1 + region.row as u16;
+        let mut x = origin.0 + region.columns.start as u16;
+        let end = origin.0 + region.columns.end as u16;
+        while x < end {
+            let next = (x + 2).min(end);
+            let text: String = (x..next)
+                .map(|cx| buffer[(cx, y)].symbol().to_string())
+                .collect();
+            buffer[(x, y)].set_symbol(&format!(
+                "\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\",
+                region.url, text
+            ));
+            for cx in x + 1..next {
+                buffer[(cx, y)].set_skip(true);
+            }
+            x = next;
+        }
+    }
+}
+
+/// Builds the visible rows of a pane column, starting at its scroll offset, and the
+/// hyperlinks in them.
+/// Wrapped continuation rows get a blank gutter so numbers and timestamps stay
+/// aligned with buffer lines.
+/// Panes showing JSON fields color each line by its log level, as do all panes
+/// with `highlight`, except for lines with ANSI colors. The keywords of
+/// `highlight` are colored, below the ANSI colors of the output, and matches of
+/// the search, if any, are highlighted.
+fn visible_rows(
+    pane: &Pane,
+    column: Column,
+    width: usize,
+    height: usize,
+    search: Option<&Search>,
+    highlight: Option<&Highlight>,
+) -> (Vec<Line<'static>>, Vec<LinkRegion>) {
+    let levels = highlight.is_some_and(|h| h.levels.unwrap_or(true));
+    let keywords = highlight.map_or(&[][..], |h| &h.keywords[..]);
+    let numbers = numbers_width(pane);
+    let timestamps = timestamps_width(pane);
+    let gutter_style = Style::default().fg(Color::DarkGray);
+    let timestamp_style = Style::default().fg(Color::Cyan);
+    let changed = Style::default().fg(Color::Black).bg(Color::Green);
+    let matched = Style::default().fg(Color::Black).bg(Color::Yellow);
+    let mut rows = Vec::with_capacity(height);
+    let mut links = Vec::new();
+    let mut skip = pane.scroll(column);
+    for (n, line) in pane.lines(column) {
+        if rows.len() >= height {
+            break;
+        }
+        let pieces = if pane.wrap {
+            wrap_text(&line.text, width)
+        } else {
+            vec![line.text.clone()]
+        };
+        if skip >= pieces.len() {
+            skip -= pieces.len();
+            continue;
+        }
+        // The keywords, the colors of the output, the changes, then the search
+        // matches on top.
+        let mut layers: Vec<(Range<usize>, Style)> = Vec::new();
+        for keyword in keywords {
+            let mut style = Style::default().fg(keyword.color.0);
+            if keyword.bold {
+                style = style.add_modifier(Modifier::BOLD);
+            }
+            layers.extend(
+                keyword
+                    .pattern
+                    .find_iter(&line.text)
+                    .map(|found| (found.range(), style)),
+            );
+        }
+        layers.extend(
+            line.styles
+                .iter()
+                .map(|styled| (styled.start..styled.end, styled.style.into())),
+        );
+        layers.extend(line.changes.iter().map(|range| (range.clone(), changed)));
+        if let Some(search) = search {
+            layers.extend(
+                search
+                    .ranges(&line.text)
+                    .into_iter()
+                    .map(|range| (range, matched)),
+            );
+        }
+        let style = if line.note {
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Cyan)
+                .add_modifier(Modifier::BOLD)
+        } else if !pane.json_fields.is_empty() || (levels && line.styles.is_empty()) {
+            severity_style(detect_severity(&line.text))
+        } else {
+            Style::default()
+        };
+        let mut offset = 0;
+        for (k, piece) in pieces.into_iter().enumerate() {
+            let start = offset;
+            offset += piece.len();
+            if k < skip {
+                continue;
+            }
+            if rows.len() >= height {
+                break;
+            }
+            if !line.links.is_empty() {
+                links.extend(link_regions(line, &piece, start, rows.len(), width));
+            }
+            let mut spans = Vec::with_capacity(3);
+            if numbers > 0 {
+                let number = if k == 0 {
+                    format!("{:>w$} ", pane.evicted + n + 1, w = numbers - 1)
+                } else {
+                    " ".repeat(numbers)
+                };
+                spans.push(Span::styled(
+                    number,
+                    gutter_style.add_modifier(Modifier::DIM),
+                ));
+            }
+            if timestamps > 0 {
+                let timestamp = match &line.timestamp {
+                    Some(timestamp) if k == 0 => {
+                        format!("{:<w$} ", timestamp, w = timestamps - 1)
+                    }
+                    _ => " ".repeat(timestamps),
+                };
+                spans.push(Span::styled(timestamp, timestamp_style));
+            }
+            if layers.is_empty() {
+                spans.push(Span::styled(piece, style));
+            } else {
+                spans.extend(styled_spans(&piece, start, style, &layers));
+            }
+            rows.push(Line::from(spans));
+        }
+        skip = 0;
+    }
+    (rows, links)
+}
+
+/// Badge for the title of a process that failed: the attempt being retried, or,
+/// once it gave up, the number of attempts and the last error.
+fn attempts_badge(status: &ProcessStatus) -> Option<String> {
+    if status.retrying {
+        if status.attempts == 0 {
+            return Some("[exited, restarting]".to_string());
+        }
+        return Some(format!("[attempt {} failed, retrying]", status.attempts));
+    }
+    if status.state != ProcessState::Failed {
+        return None;
+    }
+    let error = match (&status.spawn_error, status.exit) {
+        (Some(error), _) => error.clone(),
+        (None, Some(exit)) => format!("exited with {}", exit),
+        (None, None) => "exited".to_string(),
+    };
+    let plural = if status.attempts == 1 { "" } else { "s" };
+    Some(format!(
+        "[failed after {} attempt{}: {}]",
+        status.attempts, plural, error
+    ))
+}
+
+/// Badge telling how the last run of a process ended, while it isn't running:
+/// `[exited: CODE]` or `[killed: SIGNAL]`, with the title color to use, green
+/// for a clean exit and red otherwise.
+fn exit_badge(status: &ProcessStatus) -> Option<(String, Color)> {
+    if status.state == ProcessState::Running {
+        return None;
+    }
+    match status.exit? {
+        ExitReason::Code(0) => Some(("[exited: 0]".to_string(), Color::Green)),
+        ExitReason::Code(code) => Some((format!("[exited: {}]", code), Color::Red)),
+        signal => Some((format!("[killed: {}]", signal), Color::Red)),
+    }
+}
+
+/// Border colors of the process panes without a `color`, taken in turn. Yellow
+/// is left out, as it marks the focused pane.
+const ACCENT_PALETTE: [Color; 6] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Green,
+    Color::Blue,
+    Color::LightRed,
+    Color::LightGreen,
+];
+
+/// Creates the pane of a process, with its settings or the global defaults.
+fn process_pane(config: &Config, proc: &ProcessConfig, handle: ProcessHandle) -> Pane {
+    let position = config
+        .processes
+        .iter()
+        .position(|p| p.name == proc.name)
+        .unwrap_or_default();
+    Pane {
+        accent: proc
+            .color
+            .map_or(ACCENT_PALETTE[position % ACCENT_PALETTE.len()], |c| c.0),
+        show_uptime: config.show_uptime,
+        wrap: proc.wrap.unwrap_or(config.wrap),
+        line_numbers: proc.line_numbers.unwrap_or(config.line_numbers),
+        max_lines: line_limit(proc.max_lines.or(config.max_lines)),
+        progress_pattern: proc.progress_pattern.clone(),
+        confirm_stop: proc.confirm_stop,
+        json_fields: proc.json_fields.clone(),
+        jump_to_error: proc.jump_to_error,
+        diff_pattern: proc.diff_pattern.clone(),
+        stdin: proc.stdin,
+        checks_ready: proc.checks_ready(),
+        title_format: proc
+            .title_format
+            .clone()
+            .or_else(|| config.title_format.clone()),
+        ..Pane::new(handle.name.clone(), Some(handle))
+    }
+}
+
+/// Names of the processes that say when they are ready, watched for `on_ready`.
+fn ready_processes(config: &Config) -> Vec<String> {
+    config
+        .processes
+        .iter()
+        .filter(|p| p.checks_ready() && p.starts_automatically())
+        .map(|p| p.name.clone())
+        .collect()
+}
+
+/// Clickable label in a process pane's title that restarts the process.
+const RESTART_BUTTON: &str = "[↻]";
+
+/// Title of a process pane up to its restart button: number, name and state
+/// button, or the number and the pane's `title_format` filled in.
+fn title_head(i: usize, pane: &Pane) -> String {
+    if let (Some(format), Some(handle)) = (&pane.title_format, &pane.process) {
+        let status = handle.status.lock().unwrap();
+        return format!(
+            "{}. {}",
+            i + 1,
+            format.render(|field| title_field(pane, &status, field))
+        );
+    }
+    let button = if pane.is_active() {
+        "[Started]"
+    } else {
+        "[Stopped]"
+    };
+    format!("{}. {} {}", i + 1, pane.name, button)
+}
+
+/// Value of a `title_format` placeholder for a process pane.
+fn title_field(pane: &Pane, status: &ProcessStatus, field: TitleField) -> String {
+    match field {
+        TitleField::Name => pane.name.clone(),
+        TitleField::State => status.state.to_string(),
+        TitleField::Uptime => status
+            .started_at
+            .map_or_else(|| "-".to_string(), |t| format_duration(t.elapsed())),
+        TitleField::Exit => status
+            .exit
+            .map_or_else(|| "-".to_string(), |exit| exit.to_string()),
+        TitleField::Health => match status.state {
+            ProcessState::Running if status.is_ready() => "ready",
+            ProcessState::Running => "starting",
+            _ => "down",
+        }
+        .to_string(),
+        TitleField::Restarts => status.recent_restarts().to_string(),
+    }
+}
+
+/// Badge with how long the current run has been up and how many times the
+/// process restarted, e.g. `[up 3m12s, restarts: 2]`, for `show_uptime`. The
+/// uptime is left out while the process isn't running, and the restarts until
+/// there are some.
+fn uptime_badge(status: &ProcessStatus) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(started) = status.started_at {
+        parts.push(format!("up {}", format_duration(started.elapsed())));
+    }
+    if status.recent_restarts() > 0 {
+        parts.push(format!("restarts: {}", status.recent_restarts()));
+    }
+    (!parts.is_empty()).then(|| format!("[{}]", parts.join(", ")))
+}
+
+/// Returns whether a click at (`x`, `y`) hits the restart button in the title of
+/// the pane at index `i`. Titles start right after the top-left corner of the
+/// pane, or of its stdout column when split, which begins at the same place.
+fn hits_restart_button(i: usize, pane: &Pane, area: Rect, x: u16, y: u16) -> bool {
+    if pane.process.is_none() || y != area.y {
+        return false;
+    }
+    let head: usize = title_head(i, pane)
+        .chars()
+        .map(|c| c.width().unwrap_or(0))
+        .sum();
+    let start = area.x as usize + 1 + head + 1;
+    let end = start + RESTART_BUTTON.chars().count();
+    // The title is cut at the right border when the pane is too narrow.
+    let visible_end = (area.x + area.width).saturating_sub(1) as usize;
+    (start..end.min(visible_end)).contains(&(x as usize))
+}
+
+/// Returns whether a click at (`x`, `y`) hits the `[Started]`/`[Stopped]` button in
+/// the title of the pane at index `i`. Panes with a `title_format` have none.
+fn hits_state_button(i: usize, pane: &Pane, area: Rect, x: u16, y: u16) -> bool {
+    if pane.process.is_none() || pane.title_format.is_some() || y != area.y {
+        return false;
+    }
+    let head = title_head(i, pane);
+    let Some((before, button)) = head.rsplit_once(' ') else {
+        return false;
+    };
+    let start = area.x as usize + 1 + before.width() + 1;
+    let end = start + button.width();
+    let visible_end = (area.x + area.width).saturating_sub(1) as usize;
+    (start..end.min(visible_end)).contains(&(x as usize))
+}
+
+/// Draws each process window, including its output, title, and a vertical scrollbar.
+/// Each window shows the process name, a start/stop button, and the current output buffer.
+/// A split pane gets one block per stream, the active one highlighted.
+///
+/// # Arguments
+/// * `f` - The ratatui frame to render into.
+/// * `app` - The TUI state with every pane.
+/// * `layout` - The layout rectangles for each process window.
+fn draw_process_windows(f: &mut ratatui::Frame, app: &App, layout: &[Rect]) {
+    for (i, (pane, area)) in app.panes.iter().zip(layout).enumerate() {
+        if !app.visible.contains(&i) {
+            continue;
+        }
+        let focused = i == app.selected_window;
+        let mut title_color = None;
+        let title = match &pane.process {
+            None => format!("{}. {}", i + 1, pane.name),
+            Some(handle) => {
+                let mut title = format!("{} {}", title_head(i, pane), RESTART_BUTTON);
+                let status = handle.status.lock().unwrap();
+                if let Some((starts, at)) = pane.restarting
+                    && status.starts <= starts
+                    && at.elapsed() < RESTART_STEP_TIMEOUT
+                {
+                    title.push_str(" [restarting]");
+                }
+                if let Some((badge, color)) = exit_badge(&status) {
+                    title.push(' ');
+                    title.push_str(&badge);
+                    title_color = Some(color);
+                }
+                if status.disabled {
+                    title.push_str(" [disabled]");
+                }
+                if !status.waiting_for.is_empty() {
+                    title.push_str(&format!(
+                        " [waiting for: {}]",
+                        status.waiting_for.join(", ")
+                    ));
+                }
+                if let Some(at) = status.delayed_until {
+                    let left = at.saturating_duration_since(Instant::now());
+                    title.push_str(&format!(" [starting in {}]", format_duration(left)));
+                }
+                if status.idle
+                    && let Some(since) = status.last_output.or(status.started_at)
+                {
+                    title.push_str(&format!(
+                        " [no output for {}]",
+                        format_duration(since.elapsed())
+                    ));
+                    title_color = Some(Color::Yellow);
+                }
+                if let Some(latency) = status.startup_latency {
+                    title.push_str(&format!(" ready in {}", format_duration(latency)));
+                } else if status.ready_timed_out {
+                    title.push_str(" [not ready]");
+                    title_color = Some(Color::Red);
+                } else if pane.checks_ready
+                    && status.state == ProcessState::Running
+                    && status.delayed_until.is_none()
+                {
+                    title.push_str(" [starting]");
+                }
+                if let Some(pid) = status.daemon_pid {
+                    title.push_str(&format!(" [daemon {}]", pid));
+                }
+                if let Some(usage) = status.usage {
+                    title.push_str(&format!(" {}", usage));
+                }
+                if pane.show_uptime
+                    && let Some(badge) = uptime_badge(&status)
+                {
+                    title.push(' ');
+                    title.push_str(&badge);
+                }
+                if let Some(badge) = attempts_badge(&status) {
+                    title.push(' ');
+                    title.push_str(&badge);
+                }
+                if !pane.follow {
+                    title.push_str(" [locked, f: follow]");
+                }
+                if pane.muted {
+                    title.push_str(" [muted]");
+                }
+                if pane.paused {
+                    title.push_str(&format!(" [paused, {} new]", pane.held.len()));
+                }
+                title
+            }
+        };
+        let columns = pane_columns(pane, *area, focused);
+        let split = columns.len() > 1;
+        for (column, rect) in columns {
+            let (title, active) = match column {
+                None => (title.clone(), focused),
+                Some(Stream::Stdout) => (
+                    format!("{} stdout", title),
+                    pane.split_column == Stream::Stdout,
+                ),
+                Some(Stream::Stderr) if !split => (format!("{} [stderr only]", title), focused),
+                Some(Stream::Stderr) => ("stderr".to_string(), pane.split_column == Stream::Stderr),
+            };
+            let border_color = if active || (focused && !split) {
+                Color::Yellow
+            } else {
+                pane.accent
+            };
+            let (width, height) = text_area(pane, rect);
+            let search = app.search.as_ref().filter(|_| focused);
+            let (rows, links) =
+                visible_rows(pane, column, width, height, search, app.highlight.as_ref());
+            let mut block = Block::default()
+                .title(title.as_str())
+                .title_style(title_color.map_or_else(Style::default, |c| Style::default().fg(c)))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(border_color));
+            let below = lines_below(pane, column, rect);
+            if below > 0 {
+                block = block.title_bottom(
+                    Line::styled(new_lines_label(below), Style::default().fg(Color::Yellow))
+                        .right_aligned(),
+                );
+            }
+            let para = Paragraph::new(rows)
+                .block(block)
+                .style(Style::default().fg(Color::White));
+            f.render_widget(para, rect);
+            let origin = (rect.x + 1 + gutter_width(pane) as u16, rect.y + 1);
+            mark_hyperlinks(f.buffer_mut(), origin, &links);
+
+            let mut scrollbar_state = ScrollbarState::default()
+                .content_length(total_rows(pane, column, width))
+                .position(pane.scroll(column));
+            let scrollbar = Scrollbar::default().orientation(ScrollbarOrientation::VerticalRight);
+            f.render_stateful_widget(scrollbar, rect, &mut scrollbar_state);
+
+            if let Some(percent) = pane.progress {
+                let gauge_area = Rect {
+                    x: rect.x + 1,
+                    y: rect.y + rect.height.saturating_sub(2),
+                    width: rect.width.saturating_sub(2),
+                    height: 1,
+                };
+                let gauge = Gauge::default()
+                    .gauge_style(Style::default().fg(Color::Green).bg(Color::DarkGray))
+                    .percent(percent);
+                f.render_widget(gauge, gauge_area);
+            }
+        }
+    }
+}
+
+/// Draws the output of the ad-hoc command over the panes, if one was run.
+/// The title shows the command and whether it is still running; stderr lines are red.
+fn draw_popup(f: &mut ratatui::Frame, app: &App, area: Rect) {
+    let Some(popup) = &app.popup else {
+        return;
+    };
+    let status = popup.process.status();
+    let state = match (status.state, status.exit) {
+        (ProcessState::Running, _) => "running".to_string(),
+        (ProcessState::Exited, Some(exit)) => format!("exit {}", exit),
+        (ProcessState::Exited, None) => "exited".to_string(),
+        (ProcessState::Failed, _) => "failed".to_string(),
+        (ProcessState::Stopped, _) if status.starts > 0 => "cancelled".to_string(),
+        (ProcessState::Stopped, _) => "starting".to_string(),
+    };
+    let rows: Vec<Line> = popup
+        .buffer
+        .iter()
+        .skip(popup.scroll_offset)
+        .take(popup_height(area))
+        .map(|line| match line.stream {
+            Stream::Stdout => Line::raw(line.text.as_str()),
+            Stream::Stderr => {
+                Line::styled(line.text.as_str(), Style::default().fg(Color::LightRed))
+            }
+        })
+        .collect();
+    let rect = popup_area(area);
+    let block = Block::default()
+        .title(format!("$ {} [{}]", popup.command, state))
+        .title_bottom(
+            Line::from("Esc: close, Ctrl+C: cancel, ↑/↓: scroll").alignment(Alignment::Right),
+        )
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    f.render_widget(Clear, rect);
+    f.render_widget(Paragraph::new(rows).block(block), rect);
+}
+
+/// Draws the pending confirmation, if any, as a small box over the panes.
+fn draw_confirm(f: &mut ratatui::Frame, app: &App, area: Rect) {
+    let Some(confirm) = &app.confirm else {
+        return;
+    };
+    let text = format!("{} (y/n)", confirm.question);
+    let rect = centered(area, text.chars().count() as u16 + 4, 3);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Red));
+    f.render_widget(Clear, rect);
+    f.render_widget(
+        Paragraph::new(text)
+            .alignment(Alignment::Center)
+            .block(block),
+        rect,
+    );
+}
+
+/// Draws a prominent overlay listing the errors when no process could be started,
+/// until the user dismisses it.
+fn draw_startup_failure(f: &mut ratatui::Frame, app: &App, area: Rect) {
+    if app.failure_dismissed {
+        return;
+    }
+    let Some(failures) = app.startup_failures() else {
+        return;
+    };
+    let mut lines: Vec<Line> = failures
+        .iter()
+        .map(|(name, error)| {
+            Line::from(vec![
+                Span::styled(
+                    format!("{}: ", name),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(error.clone()),
+            ])
+        })
+        .collect();
+    lines.push(Line::raw(""));
+    lines.push(Line::raw("Check the configuration. q: quit, Esc: dismiss"));
+    let width = lines.iter().map(Line::width).max().unwrap_or(0) as u16 + 4;
+    let rect = centered(area, width, lines.len() as u16 + 2);
+    let block = Block::default()
+        .title("No process could be started")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Red));
+    f.render_widget(Clear, rect);
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(block)
+            .wrap(ratatui::widgets::Wrap { trim: false }),
+        rect,
+    );
+}
+
+/// Draws the ticker in the row above the panes: the latest line received from
+/// any process, after the process name in the accent color of its pane.
+fn draw_ticker(f: &mut ratatui::Frame, app: &App, area: Rect) {
+    if !app.show_ticker {
+        return;
+    }
+    let Some((name, text)) = &app.ticker else {
+        return;
+    };
+    let Some(pane) = app.panes.iter().find(|p| p.name == *name) else {
+        return;
+    };
+    let line = Line::from(vec![
+        Span::styled(
+            format!(" {} ", pane.name),
+            Style::default()
+                .fg(pane.accent)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(text.clone()),
+    ]);
+    f.render_widget(Paragraph::new(line), Rect { height: 1, ..area });
+}
+
+/// Draws the path of the configuration file and its modification time at the
+/// right of the row above the panes, warning when it changed since it was loaded.
+fn draw_config_info(f: &mut ratatui::Frame, app: &App, area: Rect) {
+    if !app.show_config {
+        return;
+    }
+    let info = &app.config_info;
+    let time = |t: Option<SystemTime>| {
+        t.map_or_else(
+            || "unknown".to_string(),
+            |t| {
+                chrono::DateTime::<chrono::Local>::from(t)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            },
+        )
+    };
+    let (text, style) = if info.stale() {
+        (
+            format!(
+                " {} changed on disk at {}, L: reload ",
+                app.config_path,
+                time(info.current)
+            ),
+            Style::default().fg(Color::Black).bg(Color::Yellow),
+        )
+    } else {
+        (
+            format!(" {} modified {} ", app.config_path, time(info.loaded)),
+            Style::default().fg(Color::DarkGray),
+        )
+    };
+    f.render_widget(
+        Paragraph::new(Span::styled(text, style)).alignment(Alignment::Right),
+        Rect { height: 1, ..area },
+    );
+}
+
+/// Draws a help line at the bottom of the screen with key bindings for the user,
+/// the prompt being typed, or the pending status message if there is one.
+///
+/// # Arguments
+/// * `f` - The ratatui frame to render into.
+/// * `app` - The TUI state, for the pane count, prompt and status message.
+fn draw_help_line(f: &mut ratatui::Frame, app: &App) {
+    let rect = f.area();
+    let help_area = Rect {
+        x: rect.x,
+        y: rect.y + rect.height.saturating_sub(1),
+        width: rect.width,
+        height: 1,
+    };
+    if let Some(prompt) = &app.prompt {
+        let text = format!("{}{}", prompt.label(), prompt.input);
+        let cursor = (text.chars().count() as u16).min(help_area.width.saturating_sub(1));
+        f.render_widget(Paragraph::new(text), help_area);
+        f.set_cursor_position((help_area.x + cursor, help_area.y));
+        return;
+    }
+    let keys = &app.keybindings;
+    let help = app.message.clone().unwrap_or_else(|| {
+        let page = if app.maximized {
+            format!(
+                "pane {} of {} maximized, Enter: restore ",
+                app.selected_window + 1,
+                app.panes.len()
+            )
+        } else if app.visible.len() < app.panes.len() {
+            format!(
+                "panes {}-{} of {} ",
+                app.visible.start + 1,
+                app.visible.end,
+                app.panes.len()
+            )
+        } else {
+            String::new()
+        };
+        format!(
+            "{}({}: quit, 1-{}: select process, Tab/Shift+Tab: next/previous process, {} toggle process, {}: restart, R: restart all, A/X: start/stop all, !: run command, {}/{}/PgUp/PgDn/Home/End: scroll, Shift+↑/↓: move, e: export order, d: snapshot, y/Y: copy shown/all, /: search, n/N: next/previous match, I: input, f: follow/lock, Enter: maximize, g: layout, u: mute, p: pause, m: note, t: ticker, i: config info, L: reload config, {}: clear, w: wrap, l: line numbers, s: split streams, S: stderr only)",
+            page,
+            keys.quit,
+            app.panes.len(),
+            keys.toggle,
+            keys.restart,
+            keys.scroll_up,
+            keys.scroll_down,
+            keys.clear
+        )
+    });
+    f.render_widget(
+        Paragraph::new(help)
+            .style(Style::default().fg(Color::Yellow))
+            .alignment(Alignment::Center),
+        help_area,
+    );
+}
+
+/// Handles user input events for process control and scrolling.
+/// Returns Ok(true) if the user requested to quit, otherwise Ok(false).
+///
+/// # Arguments
+/// * `app` - Mutable reference to the TUI state.
+/// * `layout` - The layout rectangles for each process window, used to clamp scrolling.
+/// * `area` - The area of all the panes, where the command popup is shown.
+///
+/// # Returns
+/// * `io::Result<bool>` - Ok(true) if quit, Ok(false) otherwise.
+fn handle_input_event(app: &mut App, layout: &[Rect], area: Rect) -> io::Result<bool> {
+    use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+    if !event::poll(Duration::from_millis(100))? {
+        return Ok(false);
+    }
+    let key = match event::read()? {
+        Event::Key(key) => key,
+        Event::Mouse(mouse) => {
+            handle_mouse_event(app, layout, mouse);
+            return Ok(false);
+        }
+        _ => return Ok(false),
+    };
+    app.message = None;
+    if !app.failure_dismissed && app.startup_failures().is_some() {
+        match key.code {
+            KeyCode::Char('q') => return Ok(true),
+            KeyCode::Esc => app.failure_dismissed = true,
+            _ => {}
+        }
+        return Ok(false);
+    }
+    if app.prompt.is_some() {
+        handle_prompt_key(app, layout, key);
+        return Ok(false);
+    }
+    if let Some(confirm) = app.confirm.take() {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Enter => match confirm.action {
+                ConfirmAction::Stop(idx) => app.panes[idx].toggle(),
+                ConfirmAction::StopAll => app.set_all_running(false),
+                ConfirmAction::Quit => return Ok(true),
+            },
+            KeyCode::Char('n') | KeyCode::Esc => {}
+            _ => app.confirm = Some(confirm),
+        }
+        return Ok(false);
+    }
+    if let Some(popup) = &mut app.popup {
+        if handle_popup_key(popup, key, popup_height(area)) {
+            app.popup = None;
+        }
+        return Ok(false);
+    }
+    // Keys bound to an action are handled as its default key.
+    let key = app.keybindings.translate(key);
+    let typed = app.pane_number.take();
+    let num_panes = app.panes.len();
+    let idx = app.selected_window;
+    let pane = &mut app.panes[idx];
+    let area = layout[idx];
+    // Column scrolled by the arrow keys.
+    let column = pane.active_column();
+    match key.code {
+        KeyCode::Char('q') if app.confirm_quit => {
+            app.confirm = Some(Confirm {
+                question: "Quit and stop all processes?".to_string(),
+                action: ConfirmAction::Quit,
+            });
+        }
+        KeyCode::Char('q') => return Ok(true),
+        KeyCode::Char(c @ '0'..='9') => {
+            let digit = c as usize - '0' as usize;
+            let number = match typed {
+                Some((typed, at))
+                    if at.elapsed() < PANE_NUMBER_TIMEOUT && typed * 10 + digit <= num_panes =>
+                {
+                    typed * 10 + digit
+                }
+                _ => digit,
+            };
+            if (1..=num_panes).contains(&number) {
+                app.pane_number = Some((number, Instant::now()));
+                app.selected_window = number - 1;
+                let idx = app.selected_window;
+                fit_scroll(&mut app.panes[idx], layout[idx], true, false);
+            }
+        }
+        KeyCode::Tab | KeyCode::BackTab => {
+            app.selected_window = if key.code == KeyCode::Tab {
+                (idx + 1) % num_panes
+            } else {
+                (idx + num_panes - 1) % num_panes
+            };
+            let idx = app.selected_window;
+            fit_scroll(&mut app.panes[idx], layout[idx], true, false);
+        }
+        KeyCode::Char(' ') if pane.stop_needs_confirmation() => {
+            app.confirm = Some(Confirm {
+                question: format!("Stop {}?", pane.name),
+                action: ConfirmAction::Stop(idx),
+            });
+        }
+        KeyCode::Char(' ') => pane.toggle(),
+        KeyCode::Char('r') => pane.restart(),
+        KeyCode::Char('A') => app.set_all_running(true),
+        KeyCode::Char('X') => {
+            if app.panes.iter().any(Pane::stop_needs_confirmation) {
+                app.confirm = Some(Confirm {
+                    question: "Stop all processes?".to_string(),
+                    action: ConfirmAction::StopAll,
+                });
+            } else {
+                app.set_all_running(false);
+            }
+        }
+        KeyCode::Enter => app.maximized = !app.maximized,
+        KeyCode::Char('L') => app.reload = true,
+        KeyCode::Char('g') => {
+            app.layout = app.layout.next();
+            app.message = Some(format!("Layout: {}", app.layout));
+        }
+        KeyCode::Esc if app.search.is_some() => app.search = None,
+        KeyCode::Esc if app.maximized => app.maximized = false,
+        KeyCode::Char('c') => pane.clear(),
+        KeyCode::Char('t') => {
+            app.show_ticker = !app.show_ticker;
+            app.ticker = None;
+        }
+        KeyCode::Char('f') => {
+            pane.follow = !pane.follow;
+            fit_scroll(pane, area, true, pane.follow);
+        }
+        KeyCode::Char('u') => pane.muted = !pane.muted,
+        KeyCode::Char('p') => pane.paused = !pane.paused,
+        KeyCode::Char('S') => {
+            pane.stderr_only = !pane.stderr_only;
+            fit_scroll(pane, area, true, pane.follow);
+        }
+        KeyCode::Char('w') => {
+            // Keeps the line at the top in view, as the number of rows changes.
+            let top = pane_columns(pane, area, true)
+                .into_iter()
+                .find(|&(c, _)| c == column || !pane.split)
+                .and_then(|(c, rect)| top_line(pane, c, rect));
+            pane.wrap = !pane.wrap;
+            match top {
+                Some(index) if !pane.follow => scroll_to_line(pane, area, true, index),
+                _ => fit_scroll(pane, area, true, pane.follow),
+            }
+        }
+        KeyCode::Char('l') => {
+            pane.line_numbers = !pane.line_numbers;
+            fit_scroll(pane, area, true, false);
+        }
+        KeyCode::Char('s') => {
+            pane.split = !pane.split;
+            fit_scroll(pane, area, true, true);
+        }
+        KeyCode::Left | KeyCode::Right if pane.split => {
+            pane.split_column = match pane.split_column {
+                Stream::Stdout => Stream::Stderr,
+                Stream::Stderr => Stream::Stdout,
+            };
+        }
+        KeyCode::Up if key.modifiers.contains(KeyModifiers::SHIFT) => app.move_selected(-1),
+        KeyCode::Down if key.modifiers.contains(KeyModifiers::SHIFT) => app.move_selected(1),
+        KeyCode::Char('e') => app.export(),
+        KeyCode::Char('d') => app.snapshot(),
+        KeyCode::Char('y') => {
+            let lines = pane_columns(pane, area, true)
+                .into_iter()
+                .find(|&(c, _)| c == column || !pane.split)
+                .map(|(c, rect)| shown_lines(pane, c, rect))
+                .unwrap_or_default();
+            let lines = lines.iter().map(|line| line.text.clone()).collect();
+            app.copy_lines(lines);
+        }
+        KeyCode::Char('Y') => {
+            let lines = pane.lines(column);
+            let skip = app.copy_last.map_or(0, |n| lines.len().saturating_sub(n));
+            let lines = lines[skip..]
+                .iter()
+                .map(|(_, line)| line.text.clone())
+                .collect();
+            app.copy_lines(lines);
+        }
+        KeyCode::Char('!') => {
+            app.prompt = Some(Prompt {
+                kind: PromptKind::Command,
+                input: String::new(),
+            });
+        }
+        KeyCode::Char('R') if app.restart_all.is_none() => {
+            app.restart_all = Some(RestartAll {
+                order: app.start_order.clone(),
+                step: 0,
+                sent: None,
+            });
+        }
+        KeyCode::Char('i') => {
+            app.show_config = !app.show_config;
+            app.config_info.refresh(&app.config_path);
+        }
+        KeyCode::Char('I') if pane.stdin => {
+            app.prompt = Some(Prompt {
+                kind: PromptKind::Input,
+                input: String::new(),
+            });
+        }
+        KeyCode::Char('I') => {
+            app.message = Some(format!("{} takes no input; set stdin: true", pane.name));
+        }
+        KeyCode::Char('m') => {
+            app.prompt = Some(Prompt {
+                kind: PromptKind::Note,
+                input: String::new(),
+            });
+        }
+        KeyCode::Char('/') => {
+            app.search_history.rewind();
+            app.prompt = Some(Prompt {
+                kind: PromptKind::Search {
+                    regex: app.search.as_ref().is_some_and(|search| search.regex),
+                },
+                input: String::new(),
+            });
+        }
+        KeyCode::Char('n') => app.find_next(layout, false),
+        KeyCode::Char('N') => app.find_next(layout, true),
+        KeyCode::Up
+        | KeyCode::Down
+        | KeyCode::PageUp
+        | KeyCode::PageDown
+        | KeyCode::Home
+        | KeyCode::End => {
+            let rect = pane_columns(pane, area, true)
+                .into_iter()
+                .find(|(c, _)| *c == column)
+                .map_or(area, |(_, rect)| rect);
+            let (_, height) = text_area(pane, rect);
+            let page = height.max(1);
+            let current = pane.scroll(column);
+            let offset = match key.code {
+                KeyCode::Up => current.saturating_sub(1),
+                KeyCode::Down => current + 1,
+                KeyCode::PageUp => current.saturating_sub(page),
+                KeyCode::PageDown => current + page,
+                KeyCode::Home => 0,
+                _ => usize::MAX,
+            };
+            set_scroll(pane, column, rect, offset);
+        }
+        _ => {}
     }
     Ok(false)
 }
+
+/// Edits the prompt being typed. Enter submits it and Escape discards it.
+fn handle_prompt_key(app: &mut App, layout: &[Rect], key: crossterm::event::KeyEvent) {
+    use crossterm::event::{KeyCode, KeyModifiers};
+    let Some(prompt) = &mut app.prompt else {
+        return;
+    };
+    match key.code {
+        KeyCode::Esc => app.prompt = None,
+        KeyCode::Enter => {
+            if let Some(prompt) = app.prompt.take() {
+                let input = prompt.input.trim();
+                match prompt.kind {
+                    PromptKind::Input => {
+                        app.send_input(layout, &prompt.input);
+                        app.prompt = Some(Prompt {
+                            kind: PromptKind::Input,
+                            input: String::new(),
+                        });
+                    }
+                    PromptKind::Command if !input.is_empty() => {
+                        app.popup = Some(Popup::new(input.to_string()));
+                    }
+                    PromptKind::Command => {}
+                    PromptKind::Search { regex } if !input.is_empty() => {
+                        app.search_history.push(input);
+                        if let Some(path) = &app.search_history_path {
+                            let _ = app.search_history.save(path);
+                        }
+                        match Search::new(input, regex) {
+                            Ok(search) => {
+                                app.search = Some(search);
+                                app.find_next(layout, false);
+                            }
+                            Err(e) => app.message = Some(format!("Invalid regex: {}", e)),
+                        }
+                    }
+                    PromptKind::Search { .. } => {}
+                    PromptKind::Note if !input.is_empty() => app.add_note(layout, input),
+                    PromptKind::Note => {}
+                }
+            }
+        }
+        KeyCode::Up if matches!(prompt.kind, PromptKind::Search { .. }) => {
+            if let Some(entry) = app.search_history.older() {
+                prompt.input = entry.to_string();
+            }
+        }
+        KeyCode::Down if matches!(prompt.kind, PromptKind::Search { .. }) => {
+            prompt.input = app.search_history.newer().unwrap_or_default().to_string();
+        }
+        KeyCode::Tab => {
+            if let PromptKind::Search { regex } = &mut prompt.kind {
+                *regex = !*regex;
+            }
+        }
+        KeyCode::Backspace => {
+            prompt.input.pop();
+        }
+        KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => prompt.input.push(c),
+        _ => {}
+    }
+}
+
+/// Handles a key while the command popup is open. Returns true when it should be closed,
+/// which also kills the command if it is still running.
+fn handle_popup_key(popup: &mut Popup, key: crossterm::event::KeyEvent, height: usize) -> bool {
+    use crossterm::event::{KeyCode, KeyModifiers};
+    let max = popup.buffer.len().saturating_sub(height);
+    match key.code {
+        KeyCode::Esc => return true,
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            popup.process.cancel()
+        }
+        KeyCode::Up => popup.scroll_offset = popup.scroll_offset.saturating_sub(1),
+        KeyCode::Down => popup.scroll_offset = (popup.scroll_offset + 1).min(max),
+        KeyCode::PageUp => popup.scroll_offset = popup.scroll_offset.saturating_sub(height),
+        KeyCode::PageDown => popup.scroll_offset = (popup.scroll_offset + height).min(max),
+        _ => {}
+    }
+    false
+}
+
+/// Handles mouse clicks: a left click on a pane's restart button restarts its
+/// process, and one on its new lines label scrolls it to the end.
+/// Clicks are ignored while a prompt, popup or confirmation is shown.
+fn handle_mouse_event(app: &mut App, layout: &[Rect], mouse: crossterm::event::MouseEvent) {
+    use crossterm::event::{MouseButton, MouseEventKind};
+    if app.prompt.is_some() || app.popup.is_some() || app.confirm.is_some() {
+        return;
+    }
+    let (x, y) = (mouse.column, mouse.row);
+    let Some((i, (pane, area))) = app
+        .panes
+        .iter_mut()
+        .zip(layout)
+        .enumerate()
+        .find(|(i, (_, area))| app.visible.contains(i) && area.contains(Position { x, y }))
+    else {
+        return;
+    };
+    match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) if hits_restart_button(i, pane, *area, x, y) => {
+            pane.restart();
+        }
+        MouseEventKind::Down(MouseButton::Left) if hits_state_button(i, pane, *area, x, y) => {
+            if pane.stop_needs_confirmation() {
+                app.confirm = Some(Confirm {
+                    question: format!("Stop {}?", pane.name),
+                    action: ConfirmAction::Stop(i),
+                });
+            } else {
+                pane.toggle();
+            }
+        }
+        MouseEventKind::Down(MouseButton::Left) => {
+            let focused = i == app.selected_window;
+            if let Some((column, rect)) = hits_new_lines(pane, *area, focused, x, y) {
+                set_scroll(pane, column, rect, usize::MAX);
+            }
+        }
+        MouseEventKind::ScrollUp | MouseEventKind::ScrollDown => {
+            let focused = i == app.selected_window;
+            let Some((column, rect)) = pane_columns(pane, *area, focused)
+                .into_iter()
+                .find(|(_, rect)| rect.contains(Position { x, y }))
+            else {
+                return;
+            };
+            let current = pane.scroll(column);
+            let offset = if mouse.kind == MouseEventKind::ScrollUp {
+                current.saturating_sub(WHEEL_LINES)
+            } else {
+                current + WHEEL_LINES
+            };
+            set_scroll(pane, column, rect, offset);
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc::{self, Receiver};
+
+    fn config(yaml: &str) -> Config {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    /// The handle of a process named `name`, with the receiving end of its
+    /// commands and the sending end of its output.
+    fn handle(
+        name: &str,
+    ) -> (
+        ProcessHandle,
+        Receiver<ProcessCommand>,
+        mpsc::Sender<OutputLine>,
+    ) {
+        let (control, commands) = mpsc::channel(16);
+        let (lines, output) = mpsc::channel(256);
+        let handle = ProcessHandle {
+            name: name.to_string(),
+            output,
+            control,
+            status: Default::default(),
+        };
+        (handle, commands, lines)
+    }
+
+    const AREA: Rect = Rect {
+        x: 0,
+        y: 0,
+        width: 80,
+        height: 20,
+    };
+
+    #[test]
+    fn notes_are_inserted_and_sent_to_be_logged() {
+        let config = config("processes: [{name: api, cmd: 'true'}]");
+        let (api, mut commands, _lines) = handle("api");
+        let mut app = App::new(&config, "runner.yaml", vec![api]);
+        app.add_note(&[AREA], "reproducing");
+        let line = app.panes[0].buffer.last().unwrap();
+        assert!(line.note);
+        assert!(line.text.ends_with(" note: reproducing ──"));
+        match commands.try_recv() {
+            Ok(ProcessCommand::Note(text)) => assert_eq!(text, line.text),
+            _ => panic!("the note wasn't sent to the process"),
+        }
+    }
+
+    /// Sends lines with the given texts as output of the process.
+    fn send(lines: &mpsc::Sender<OutputLine>, texts: &[&str]) {
+        for text in texts {
+            lines
+                .try_send(OutputLine::new(Stream::Stdout, *text))
+                .unwrap();
+        }
+    }
+
+    fn texts(lines: &[OutputLine]) -> Vec<&str> {
+        lines.iter().map(|line| line.text.as_str()).collect()
+    }
+
+    #[test]
+    fn muted_lines_are_discarded_and_later_ones_kept() {
+        let (api, _commands, lines) = handle("api");
+        let mut pane = Pane::new("api".to_string(), Some(api));
+        pane.muted = true;
+        send(&lines, &["noise", "more noise"]);
+        assert!(take_incoming(&mut pane).is_empty());
+        pane.muted = false;
+        send(&lines, &["signal"]);
+        assert_eq!(texts(&take_incoming(&mut pane)), ["signal"]);
+    }
+
+    #[test]
+    fn buffers_and_held_lines_stay_within_the_budget() {
+        let config = config(
+            "
+            buffer_budget: 20000
+            processes:
+              - {name: flood, cmd: 'true', max_lines: 0}
+              - {name: paused, cmd: 'true', max_lines: 0}
+              - {name: quiet, cmd: 'true', max_lines: 0}
+            ",
+        );
+        let (flood, _flood_commands, flood_lines) = handle("flood");
+        let (paused, _paused_commands, paused_lines) = handle("paused");
+        let (quiet, _quiet_commands, quiet_lines) = handle("quiet");
+        let mut app = App::new(&config, "runner.yaml", vec![flood, paused, quiet]);
+        app.panes[1].paused = true;
+        let layout = [AREA; 3];
+        let line = "x".repeat(100);
+        send(&quiet_lines, &["hello"]);
+        for _ in 0..10 {
+            send(&flood_lines, &[line.as_str(); 100]);
+            send(&paused_lines, &[line.as_str(); 50]);
+            update_buffers_and_scroll(&mut app, &layout);
+            let total: usize = app.panes.iter().map(Pane::memory).sum();
+            assert!(total <= 20000, "{total} bytes kept");
+        }
+        assert!(!app.panes[1].held.is_empty());
+        assert_eq!(texts(&app.panes[2].buffer), ["hello"]);
+    }
+
+    #[test]
+    fn paused_lines_are_held_until_resuming() {
+        let (api, _commands, lines) = handle("api");
+        let mut pane = Pane::new("api".to_string(), Some(api));
+        pane.paused = true;
+        send(&lines, &["one", "two"]);
+        assert!(take_incoming(&mut pane).is_empty());
+        assert_eq!(texts(&pane.held), ["one", "two"]);
+        pane.paused = false;
+        send(&lines, &["three"]);
+        assert_eq!(texts(&take_incoming(&mut pane)), ["one", "two", "three"]);
+        assert!(pane.held.is_empty());
+    }
+
+    fn pattern(source: &str) -> Pattern {
+        serde_yaml::from_value(serde_yaml::Value::String(source.to_string())).unwrap()
+    }
+
+    #[test]
+    fn progress_is_read_as_a_percentage_or_steps() {
+        let percent = pattern(r"(\d+)%");
+        assert_eq!(parse_progress(&percent, "downloading 42%"), Some(42));
+        assert_eq!(parse_progress(&percent, "downloading 250%"), Some(100));
+        assert_eq!(parse_progress(&percent, "no progress here"), None);
+        let steps = pattern(r"(\d+)/(\d+)");
+        assert_eq!(parse_progress(&steps, "step 3/10"), Some(30));
+        assert_eq!(parse_progress(&steps, "step 12/10"), Some(100));
+        assert_eq!(parse_progress(&steps, "step 3/0"), None);
+        let signed = pattern(r"(-?\d+)%");
+        assert_eq!(parse_progress(&signed, "-5%"), Some(0));
+    }
+
+    /// Changes the status of the process of a pane, as its supervisor would.
+    fn set_status(pane: &Pane, change: impl FnOnce(&mut ProcessStatus)) {
+        change(&mut pane.process.as_ref().unwrap().status.lock().unwrap());
+    }
+
+    fn sent_start(commands: &mut Receiver<ProcessCommand>) -> Option<bool> {
+        match commands.try_recv().ok()? {
+            ProcessCommand::Start => Some(true),
+            ProcessCommand::Stop => Some(false),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn only_stopping_a_running_process_with_confirm_stop_is_confirmed() {
+        let config = config(
+            "
+            processes:
+              - {name: db, cmd: 'true', confirm_stop: true}
+              - {name: api, cmd: 'true'}
+            ",
+        );
+        let (db, _db_commands, _db_lines) = handle("db");
+        let (api, _api_commands, _api_lines) = handle("api");
+        let app = App::new(&config, "runner.yaml", vec![db, api]);
+        assert!(!app.panes[0].stop_needs_confirmation());
+        for pane in &app.panes {
+            set_status(pane, |status| status.state = ProcessState::Running);
+        }
+        assert!(app.panes[0].stop_needs_confirmation());
+        assert!(!app.panes[1].stop_needs_confirmation());
+        set_status(&app.panes[0], |status| status.state = ProcessState::Exited);
+        assert!(!app.panes[0].stop_needs_confirmation());
+        let mut without_process = Pane::new("db".to_string(), None);
+        without_process.confirm_stop = true;
+        assert!(!without_process.stop_needs_confirmation());
+    }
+
+    #[test]
+    fn a_process_that_ended_on_its_own_shows_as_stopped_and_starts_again() {
+        let config = config("processes: [{name: api, cmd: 'true'}]");
+        let (api, mut commands, _lines) = handle("api");
+        let mut app = App::new(&config, "runner.yaml", vec![api]);
+        set_status(&app.panes[0], |status| status.state = ProcessState::Running);
+        assert_eq!(title_head(0, &app.panes[0]), "1. api [Started]");
+        app.panes[0].toggle();
+        assert_eq!(sent_start(&mut commands), Some(false));
+        set_status(&app.panes[0], |status| {
+            status.state = ProcessState::Exited;
+            status.exit = Some(ExitReason::Code(1));
+        });
+        assert_eq!(title_head(0, &app.panes[0]), "1. api [Stopped]");
+        app.panes[0].toggle();
+        assert_eq!(sent_start(&mut commands), Some(true));
+        // Until a scheduled retry is stopped, the process counts as running.
+        set_status(&app.panes[0], |status| status.retrying = true);
+        app.panes[0].toggle();
+        assert_eq!(sent_start(&mut commands), Some(false));
+    }
+
+    #[test]
+    fn all_failed_only_when_every_automatic_start_failed() {
+        let failed = ProcessStatus {
+            state: ProcessState::Failed,
+            spawn_error: Some("not found".to_string()),
+            ..Default::default()
+        };
+        let retrying = ProcessStatus {
+            retrying: true,
+            ..failed.clone()
+        };
+        let ran = ProcessStatus {
+            starts: 1,
+            ..failed.clone()
+        };
+        let on_demand = ProcessStatus {
+            on_demand: true,
+            ..Default::default()
+        };
+        let named = |statuses: &[&ProcessStatus]| -> Vec<(String, ProcessStatus)> {
+            statuses
+                .iter()
+                .map(|status| ("p".to_string(), (*status).clone()))
+                .collect()
+        };
+        assert!(all_failed(&named(&[&failed, &failed])));
+        assert!(all_failed(&named(&[&failed, &on_demand])));
+        assert!(!all_failed(&named(&[&failed, &retrying])));
+        assert!(!all_failed(&named(&[&failed, &ran])));
+        assert!(!all_failed(&named(&[&on_demand])));
+        assert!(!all_failed(&[]));
+    }
+
+    #[test]
+    fn an_error_after_healthy_output_starts_a_failure() {
+        let mut pane = Pane::new("api".to_string(), None);
+        let now = Instant::now();
+        assert!(!pane.error_starts_failure(now));
+        pane.jump_to_error = true;
+        pane.last_error = None;
+        assert!(pane.error_starts_failure(now));
+        assert!(!pane.error_starts_failure(now + Duration::from_secs(1)));
+        assert!(pane.error_starts_failure(now + Duration::from_secs(1) + HEALTHY_PERIOD));
+        pane.follow = false;
+        assert!(!pane.error_starts_failure(now + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn a_new_failure_scrolls_to_its_first_error() {
+        let config = config("processes: [{name: api, cmd: 'true', jump_to_error: true}]");
+        let (api, _commands, lines) = handle("api");
+        let mut app = App::new(&config, "runner.yaml", vec![api]);
+        let healthy: Vec<String> = (0..30).map(|i| format!("request {}", i)).collect();
+        send(
+            &lines,
+            &healthy.iter().map(String::as_str).collect::<Vec<_>>(),
+        );
+        send(&lines, &["ERROR connection refused", "ERROR retrying"]);
+        send(
+            &lines,
+            &healthy.iter().map(String::as_str).collect::<Vec<_>>(),
+        );
+        update_buffers_and_scroll(&mut app, &[AREA]);
+        let pane = &app.panes[0];
+        assert!(!pane.follow);
+        assert_eq!(pane.scroll_offset, 30);
+    }
+
+    #[test]
+    fn restart_all_stops_in_reverse_order_then_starts_in_order() {
+        let config = config(
+            "
+            processes:
+              - {name: api, cmd: 'true', depends_on: [db]}
+              - {name: db, cmd: 'true'}
+            ",
+        );
+        let (api, api_commands, _api_lines) = handle("api");
+        let (db, db_commands, _db_lines) = handle("db");
+        let mut app = App::new(&config, "runner.yaml", vec![api, db]);
+        for pane in &app.panes {
+            let mut status = pane.process.as_ref().unwrap().status.lock().unwrap();
+            status.state = ProcessState::Running;
+            status.starts = 1;
+        }
+        app.restart_all = Some(RestartAll {
+            order: app.start_order.clone(),
+            step: 0,
+            sent: None,
+        });
+        let mut receivers = [("api", api_commands), ("db", db_commands)];
+        let mut sent = Vec::new();
+        while app.restart_all.is_some() {
+            update_restart_all(&mut app);
+            for (name, commands) in &mut receivers {
+                while let Ok(command) = commands.try_recv() {
+                    let stopping = matches!(command, ProcessCommand::Stop);
+                    sent.push(format!(
+                        "{} {}",
+                        if stopping { "stop" } else { "start" },
+                        name
+                    ));
+                    // The process acts on the command right away.
+                    let pane = app.panes.iter().find(|p| p.name == *name).unwrap();
+                    let mut status = pane.process.as_ref().unwrap().status.lock().unwrap();
+                    if stopping {
+                        status.state = ProcessState::Stopped;
+                    } else {
+                        status.state = ProcessState::Running;
+                        status.starts += 1;
+                    }
+                }
+            }
+        }
+        assert_eq!(sent, ["stop api", "stop db", "start db", "start api"]);
+        assert_eq!(app.message.as_deref(), Some("Restarted all processes"));
+    }
+
+    #[test]
+    fn on_ready_fires_once_every_checked_process_is_ready() {
+        let config = config(
+            "
+            on_ready: {message: Stack is up}
+            processes:
+              - {name: db, cmd: 'true', ready_when: accepting}
+              - {name: api, cmd: 'true', ready_when: listening}
+              - {name: worker, cmd: 'true'}
+            ",
+        );
+        let handles = ["db", "api", "worker"].map(|name| handle(name).0);
+        let statuses: Vec<_> = handles.iter().map(|h| h.status.clone()).collect();
+        let mut app = App::new(&config, "runner.yaml", handles.into());
+        let set_ready = |i: usize, ready: bool| {
+            let mut status = statuses[i].lock().unwrap();
+            status.state = if ready {
+                ProcessState::Running
+            } else {
+                ProcessState::Stopped
+            };
+            status.checks_ready = true;
+            status.startup_latency = ready.then(|| Duration::from_millis(10));
+        };
+        set_ready(0, true);
+        update_ready_gate(&mut app);
+        assert_eq!(app.message, None);
+        set_ready(1, true);
+        update_ready_gate(&mut app);
+        assert!(
+            app.message
+                .take()
+                .unwrap()
+                .starts_with("Stack is up (after ")
+        );
+        update_ready_gate(&mut app);
+        assert_eq!(app.message, None);
+        // The stack goes down and comes back up.
+        set_ready(1, false);
+        update_ready_gate(&mut app);
+        set_ready(1, true);
+        update_ready_gate(&mut app);
+        assert!(app.message.is_some());
+    }
+
+    #[test]
+    fn start_and_stop_all_go_by_the_state_of_each_process() {
+        let config = config(
+            "
+            processes:
+              - {name: api, cmd: 'true'}
+              - {name: worker, cmd: 'true'}
+              - {name: cron, cmd: 'true'}
+              - {name: seed, cmd: 'true', enabled: false}
+            ",
+        );
+        let handles = ["api", "worker", "cron", "seed"].map(handle);
+        let mut commands = Vec::new();
+        let mut channels = Vec::new();
+        for (handle, receiver, _lines) in handles {
+            channels.push(handle);
+            commands.push(receiver);
+        }
+        let mut app = App::new(&config, "runner.yaml", channels);
+        set_status(&app.panes[0], |status| status.state = ProcessState::Running);
+        set_status(&app.panes[1], |status| status.state = ProcessState::Exited);
+        set_status(&app.panes[2], |status| status.state = ProcessState::Failed);
+        set_status(&app.panes[3], |status| status.disabled = true);
+        app.set_all_running(true);
+        let sent: Vec<Option<bool>> = commands.iter_mut().map(sent_start).collect();
+        assert_eq!(sent, [None, Some(true), Some(true), None]);
+        app.set_all_running(false);
+        let sent: Vec<Option<bool>> = commands.iter_mut().map(sent_start).collect();
+        assert_eq!(sent, [Some(false), None, None, None]);
+    }
+
+    #[tokio::test]
+    async fn a_process_that_failed_to_spawn_shows_as_stopped() {
+        let config = config(
+            "processes: [{name: typo, command: starter-no-such-binary, confirm_stop: true}]",
+        );
+        let (channels, mut manager) = crate::process::spawn_process(&config, None).await.unwrap();
+        channels[0]
+            .control
+            .send(ProcessCommand::Start)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let app = App::new(&config, "runner.yaml", channels);
+        let pane = &app.panes[0];
+        set_status(pane, |status| {
+            assert_eq!(status.state, ProcessState::Failed)
+        });
+        assert_eq!(title_head(0, pane), "1. typo [Stopped]");
+        assert!(!pane.stop_needs_confirmation());
+        manager.stop_all();
+    }
+
+    /// The symbols of a row that are drawn, skipping the cells covered by the one before.
+    fn drawn_row(buffer: &ratatui::buffer::Buffer, y: u16) -> String {
+        (0..buffer.area.width)
+            .map(|x| &buffer[(x, y)])
+            .filter(|cell| !cell.skip)
+            .map(|cell| cell.symbol())
+            .collect()
+    }
+
+    #[test]
+    fn hyperlinks_round_trip_to_the_terminal() {
+        for raw in [
+            "\x1b]8;;https://x\x1b\\ok\x1b]8;;\x1b\\",
+            "\x1b]8;;https://x\x07ok\x1b]8;;\x07",
+        ] {
+            let (text, links) = crate::ansi::extract_hyperlinks(raw);
+            assert_eq!(text, "ok");
+            let mut buffer = ratatui::buffer::Buffer::empty(Rect::new(0, 0, 2, 1));
+            buffer.set_string(0, 0, &text, Style::default());
+            let regions = [LinkRegion {
+                row: 0,
+                columns: links[0].start..links[0].end,
+                url: links[0].url.clone(),
+            }];
+            mark_hyperlinks(&mut buffer, (0, 0), &regions);
+            assert_eq!(
+                drawn_row(&buffer, 0),
+                "\x1b]8;;https://x\x1b\\ok\x1b]8;;\x1b\\"
+            );
+        }
+    }
+
+    #[test]
+    fn long_hyperlinks_are_marked_in_pairs_of_cells() {
+        let mut buffer = ratatui::buffer::Buffer::empty(Rect::new(0, 0, 6, 1));
+        buffer.set_string(0, 0, "see it", Style::default());
+        let regions = [LinkRegion {
+            row: 0,
+            columns: 0..6,
+            url: "https://x".to_string(),
+        }];
+        mark_hyperlinks(&mut buffer, (0, 0), &regions);
+        let (text, links) = crate::ansi::extract_hyperlinks(&drawn_row(&buffer, 0));
+        assert_eq!(text, "see it");
+        let covered: Vec<(usize, usize)> = links.iter().map(|l| (l.start, l.end)).collect();
+        assert_eq!(covered, [(0, 2), (2, 4), (4, 6)]);
+        assert!(links.iter().all(|link| link.url == "https://x"));
+    }
+
+    #[test]
+    fn the_ticker_keeps_its_process_when_panes_move() {
+        let config = config(
+            "
+            ticker: true
+            processes:
+              - {name: api, cmd: 'true', color: red}
+              - {name: db, cmd: 'true'}
+            ",
+        );
+        let (api, _api_commands, api_lines) = handle("api");
+        let (db, _db_commands, _db_lines) = handle("db");
+        let mut app = App::new(&config, "runner.yaml", vec![api, db]);
+        send(&api_lines, &["listening"]);
+        update_buffers_and_scroll(&mut app, &[AREA; 2]);
+        app.move_selected(1);
+        assert_eq!(app.panes[1].name, "api");
+        let mut terminal =
+            ratatui::Terminal::new(ratatui::backend::TestBackend::new(40, 1)).unwrap();
+        terminal.draw(|f| draw_ticker(f, &app, f.area())).unwrap();
+        let buffer = terminal.backend().buffer();
+        let row: String = (0..40).map(|x| buffer[(x, 0)].symbol()).collect();
+        assert_eq!(row.trim_end(), " api listening");
+        assert_eq!(buffer[(1, 0)].fg, Color::Red);
+    }
+
+    #[test]
+    fn a_failure_replacing_a_line_scrolls_to_that_line() {
+        let config = config("processes: [{name: api, cmd: 'true', jump_to_error: true}]");
+        let (api, _commands, lines) = handle("api");
+        let mut app = App::new(&config, "runner.yaml", vec![api]);
+        let healthy: Vec<String> = (0..30).map(|i| format!("request {}", i)).collect();
+        let healthy: Vec<&str> = healthy.iter().map(String::as_str).collect();
+        send(&lines, &healthy);
+        lines
+            .try_send(OutputLine {
+                replaces: true,
+                ..OutputLine::new(Stream::Stdout, "ERROR connection refused")
+            })
+            .unwrap();
+        send(&lines, &healthy);
+        update_buffers_and_scroll(&mut app, &[AREA]);
+        let pane = &app.panes[0];
+        assert_eq!(pane.buffer[29].text, "ERROR connection refused");
+        assert!(!pane.follow);
+        assert_eq!(pane.scroll_offset, 29);
+    }
+}
@@ -0,0 +1,124 @@
+use std::time::Instant;
+
+/// CPU and memory taken by a process and the processes it started.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Usage {
+    /// Share of one CPU used since the previous sample; above 100 on several cores.
+    pub cpu_percent: f64,
+    /// Resident memory, in bytes.
+    pub rss: u64,
+}
+
+impl std::fmt::Display for Usage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "cpu {:.0}% mem {}",
+            self.cpu_percent,
+            format_bytes(self.rss)
+        )
+    }
+}
+
+/// Totals of a process group at one moment, to compare with the next ones.
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    /// CPU time used so far by the processes in the group, in clock ticks.
+    ticks: u64,
+    rss: u64,
+    at: Instant,
+}
+
+impl Sample {
+    /// Usage between this sample and an earlier one of the same group. Time
+    /// used by processes that ended in between is not counted.
+    pub fn usage_since(&self, earlier: &Sample) -> Usage {
+        let elapsed = self.at.saturating_duration_since(earlier.at).as_secs_f64();
+        let used = self.ticks.saturating_sub(earlier.ticks) as f64 / clock_ticks_per_second();
+        Usage {
+            cpu_percent: if elapsed > 0.0 {
+                used / elapsed * 100.0
+            } else {
+                0.0
+            },
+            rss: self.rss,
+        }
+    }
+}
+
+/// Adds up the CPU time and resident memory of every process in a process
+/// group, from `/proc`. Returns `None` where that isn't available.
+#[cfg(target_os = "linux")]
+pub fn sample_group(pgid: i32) -> Option<Sample> {
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(0) as u64;
+    let mut ticks = 0;
+    let mut pages = 0;
+    for entry in std::fs::read_dir("/proc").ok()?.flatten() {
+        if !entry
+            .file_name()
+            .to_string_lossy()
+            .bytes()
+            .all(|b| b.is_ascii_digit())
+        {
+            continue;
+        }
+        // Processes may end while being read; they are just left out.
+        let Ok(stat) = std::fs::read_to_string(entry.path().join("stat")) else {
+            continue;
+        };
+        // The command name may hold spaces and parentheses, so fields are
+        // counted from the last ')': state, ppid, pgrp, ... utime, stime, ... rss.
+        let Some((_, rest)) = stat.rsplit_once(')') else {
+            continue;
+        };
+        let fields: Vec<&str> = rest.split_whitespace().collect();
+        let field = |i: usize| fields.get(i).and_then(|f| f.parse::<u64>().ok());
+        if fields.get(2).and_then(|f| f.parse::<i32>().ok()) != Some(pgid) {
+            continue;
+        }
+        ticks += field(11).unwrap_or(0) + field(12).unwrap_or(0);
+        pages += field(21).unwrap_or(0);
+    }
+    Some(Sample {
+        ticks,
+        rss: pages * page_size,
+        at: Instant::now(),
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn sample_group(_pgid: i32) -> Option<Sample> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn clock_ticks_per_second() -> f64 {
+    match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+        ticks if ticks > 0 => ticks as f64,
+        _ => 100.0,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn clock_ticks_per_second() -> f64 {
+    100.0
+}
+
+/// Formats a number of bytes with the largest unit that keeps it above 1, e.g. `84MB`.
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = "B";
+    for next in UNITS {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next;
+    }
+    if value < 10.0 && unit != "B" {
+        format!("{:.1}{}", value, unit)
+    } else {
+        format!("{:.0}{}", value, unit)
+    }
+}